@@ -0,0 +1,40 @@
+use std::env::current_dir;
+use std::fs::{create_dir_all, write};
+
+use schemars::schema_for;
+
+use token_distributor::msg::{
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, FeeInfoResponse, InstantiateMsg, MigrateMsg, OwnerResponse, PreviewResponse, PreviewWithdrawResponse, QueryMsg,
+    StatsResponse, SudoMsg, WithdrawResponse, WithdrawableResponse,
+};
+use token_distributor::state::DepositRecord;
+
+// Writes the JSON schemas of the messages and responses into ./schema
+fn main() {
+    let mut out_dir = current_dir().unwrap();
+    out_dir.push("schema");
+    create_dir_all(&out_dir).unwrap();
+
+    let schemas = [
+        ("instantiate_msg", schema_for!(InstantiateMsg)),
+        ("execute_msg", schema_for!(ExecuteMsg)),
+        ("query_msg", schema_for!(QueryMsg)),
+        ("migrate_msg", schema_for!(MigrateMsg)),
+        ("sudo_msg", schema_for!(SudoMsg)),
+        ("cw20_hook_msg", schema_for!(Cw20HookMsg)),
+        ("config_response", schema_for!(ConfigResponse)),
+        ("fee_info_response", schema_for!(FeeInfoResponse)),
+        ("owner_response", schema_for!(OwnerResponse)),
+        ("stats_response", schema_for!(StatsResponse)),
+        ("withdrawable_response", schema_for!(WithdrawableResponse)),
+        ("withdraw_response", schema_for!(WithdrawResponse)),
+        ("preview_response", schema_for!(PreviewResponse)),
+        ("preview_withdraw_response", schema_for!(PreviewWithdrawResponse)),
+        ("deposit_record", schema_for!(DepositRecord)),
+    ];
+    for (name, schema) in schemas {
+        let path = out_dir.join(format!("{}.json", name));
+        write(&path, serde_json::to_string_pretty(&schema).unwrap()).unwrap();
+        println!("Created {}", path.to_str().unwrap());
+    }
+}
@@ -1,190 +1,2697 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, WasmMsg, Uint128,
+    from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, Event, MessageInfo, Order,
+    Reply, Response, StdError, StdResult, Storage, SubMsg, SubMsgResult, Timestamp, WasmMsg, Uint128,
 };
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw2::{set_contract_version, CONTRACT};
+use cw4::{Cw4QueryMsg, MemberListResponse};
+use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw_storage_plus::{Bound, Item, Map, PrimaryKey};
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
 
 use crate::error::ContractError;
-use crate::msg::{Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{ContractInfo, CONTRACT_INFO, WITHDRAWABLE, FEE_COLLECTED};
+use crate::msg::{
+    ConfigResponse, Cw20HookMsg, DepositMsg, DustPolicy, ExecuteMsg, FeeInfoResponse, FeeMode, InstantiateMsg, MigrateMsg, QueryMsg,
+    OwnerResponse, PreviewResponse, PreviewWithdrawResponse, RemainderTo, RoundingMode, StatsResponse, SudoMsg, VestingSchedule, WithdrawResponse, WithdrawableResponse,
+};
+use crate::state::{
+    ContractInfo, CONTRACT_INFO, PENDING_OWNER, TOTAL_EARNED, WITHDRAWABLE, FEE_COLLECTED,
+    NATIVE_FEE_COLLECTED, NATIVE_WITHDRAWABLE, TOKEN_FEE_COLLECTED, TOKEN_WITHDRAWABLE,
+    LAST_REPLY_ID, PENDING_WITHDRAWALS, PendingWithdrawal, DEPOSITORS, LOCKED,
+    VESTING, VestingEntry, TOTAL_WITHDRAWABLE, OPERATORS, FROZEN,
+    LAST_WITHDRAWAL, DEPOSIT_COUNT, DEPOSITS, DepositRecord, SHARES, TOKEN_CONFIGS, TokenConfig,
+    NONCES, RECIPIENT_DEPOSITS, TOTAL_DEPOSITED, TOTAL_WITHDRAWN, TOTAL_FEES, LEGACY_CONTRACT_INFO,
+};
+
+// version info for migration
+pub const CONTRACT_NAME: &str = "crates.io:cosmwasm-token-distributor";
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// pagination bounds for list queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+// most addresses a WithdrawableBatch query can ask for
+const MAX_BATCH: usize = 50;
+const DEFAULT_MAX_RECIPIENTS: u32 = 20;
+
+// members of a group read per recipient allowed, zero weights included, before a deposit gives up
+const GROUP_FETCH_FACTOR: usize = 4;
+
+// splits are expressed in basis points of the deposited amount
+const BPS_DENOMINATOR: u16 = 10000u16;
+
+// fees are a fraction of the deposited amount, 5% unless configured otherwise
+const DEFAULT_FEE_NUMERATOR: u64 = 50;
+const DEFAULT_FEE_DENOMINATOR: u64 = 1000;
+
+// reply id of fee burns, withdrawal ids start at 1
+const BURN_FEE_REPLY_ID: u64 = 0;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    let contract_info = ContractInfo {
-        token: deps.api.addr_validate(&msg.token)?,
-        owner: deps.api.addr_validate(&msg.owner)?,
-    };
+    let shareholders = msg.shareholders.clone().unwrap_or_default();
+    let contract_info = contract_info_from(deps.as_ref(), msg)?;
     CONTRACT_INFO.save(deps.storage, &contract_info)?;
+    save_shares(deps.branch(), &env, &contract_info, shareholders)?;
     FEE_COLLECTED.save(deps.storage, &Uint128::zero())?;
+    TOTAL_WITHDRAWABLE.save(deps.storage, &Uint128::zero())?;
+    TOTAL_DEPOSITED.save(deps.storage, &Uint128::zero())?;
+    TOTAL_WITHDRAWN.save(deps.storage, &Uint128::zero())?;
+    TOTAL_FEES.save(deps.storage, &Uint128::zero())?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new().add_attribute("method", "instantiate"))
 }
 
+// Validates the settings of an InstantiateMsg, unset ones take their default
+fn contract_info_from(deps: Deps, msg: InstantiateMsg) -> Result<ContractInfo, ContractError> {
+    // the default fee applies only when none of the fee fields is set
+    let (fee_numerator, fee_denominator) = match (msg.fee_numerator, msg.fee_denominator, msg.fee_bps) {
+        (None, None, None) => (DEFAULT_FEE_NUMERATOR, DEFAULT_FEE_DENOMINATOR),
+        (fee_numerator, fee_denominator, fee_bps) => fee_ratio(fee_numerator, fee_denominator, fee_bps)?,
+    };
+    validate_fee(fee_numerator, fee_denominator)?;
+    let withdraw_fee_bps = msg.withdraw_fee_bps.unwrap_or_default();
+    validate_fee(withdraw_fee_bps as u64, BPS_DENOMINATOR as u64)?;
+    if msg.split_bps > BPS_DENOMINATOR {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Invalid split".to_string(),
+        }));
+    }
+
+    let (token, token_info) = validate_token(deps, &msg.token)?;
+    let owner = deps.api.addr_validate(&msg.owner)?;
+    if owner == token {
+        return Err(ContractError::OwnerIsToken {});
+    }
+
+    Ok(ContractInfo {
+        token,
+        owner: owner.clone(),
+        fee_numerator,
+        fee_denominator,
+        split_bps: msg.split_bps,
+        native_denom: msg.native_denom,
+        min_deposit: msg.min_deposit.unwrap_or_default(),
+        rounding: msg.rounding.unwrap_or_default(),
+        fee_mode: match msg.fee_mode.unwrap_or_default() {
+            FeeMode::Forward { recipient } => FeeMode::Forward {
+                recipient: deps.api.addr_validate(&recipient)?.to_string(),
+            },
+            FeeMode::Accrue => FeeMode::Accrue,
+        },
+        withdraw_cooldown: msg.withdraw_cooldown.unwrap_or_default(),
+        max_fee: msg.max_fee,
+        burn_fee: msg.burn_fee.unwrap_or_default(),
+        max_total_withdrawable: msg.max_total_withdrawable,
+        remainder_to: msg.remainder_to.unwrap_or_default(),
+        recovery_addr: msg.recovery_addr.map(|addr| deps.api.addr_validate(&addr)).transpose()?,
+        decimals: token_info.decimals,
+        symbol: token_info.symbol,
+        withdraw_fee_bps,
+        forbid_owner_recipient: msg.forbid_owner_recipient.unwrap_or_default(),
+        deflationary_token: msg.deflationary_token.unwrap_or_default(),
+        check_contract_balance: msg.check_contract_balance.unwrap_or_default(),
+        fee_recipient: owner,
+        max_recipients: msg.max_recipients.unwrap_or(DEFAULT_MAX_RECIPIENTS),
+        group: msg.group.map(|addr| deps.api.addr_validate(&addr)).transpose()?,
+        dust_policy: msg.dust_policy.unwrap_or_default(),
+    })
+}
+
+// Invariant: handlers save every balance and fee change before returning, and the messages they
+// return only run once they have, so a transfer or hook calling back into the contract always
+// sees the debited state. Handlers may still read their own writes back or query the chain,
+// e.g. the deposit cap and the contract balance check, as neither runs a message early.
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Withdraw { amount } => _withdraw(deps, info, amount),
-        ExecuteMsg::WithdrawAll {} => withdraw_all(deps, info),
-        ExecuteMsg::WithdrawFee {} => withdraw_fee(deps, info),
-        ExecuteMsg::Receive(msg) => deposit(deps, info, msg),
+        ExecuteMsg::Withdraw { amount, recipient, forward_contract, forward_msg } => {
+            withdraw(deps, env, info, amount, recipient, forward_contract, forward_msg)
+        }
+        ExecuteMsg::WithdrawAll {} => withdraw_all(deps, env, info),
+        ExecuteMsg::WithdrawPercent { bps } => withdraw_percent(deps, env, info, bps),
+        ExecuteMsg::WithdrawAndExecute { amount, contract, msg } => {
+            _withdraw(deps, env, info.sender, amount, Some(contract), Some(msg))
+        }
+        ExecuteMsg::WithdrawFee { recipient, amount } => withdraw_fee(deps, env, info, recipient, amount),
+        ExecuteMsg::Receive(msg) => deposit(deps, env, info, msg),
+        ExecuteMsg::TransferOwnership { new_owner } => transfer_ownership(deps, info, new_owner),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::DepositNative(msg) => deposit_native(deps, env, info, msg),
+        ExecuteMsg::WithdrawNative { amount, recipient } => withdraw_native(deps, info, amount, recipient),
+        ExecuteMsg::WithdrawNativeFee { recipient } => withdraw_native_fee(deps, info, recipient),
+        ExecuteMsg::WithdrawToken { token, amount, recipient } => withdraw_token(deps, info, token, amount, recipient),
+        ExecuteMsg::WithdrawTokenFee { token, recipient } => withdraw_token_fee(deps, info, token, recipient),
+        ExecuteMsg::SweepUnallocated {} => sweep_unallocated(deps, env, info),
+        ExecuteMsg::UpdateToken { token } => update_token(deps, info, token),
+        ExecuteMsg::AddDepositor { addr } => update_depositor(deps, info, addr, true),
+        ExecuteMsg::RemoveDepositor { addr } => update_depositor(deps, info, addr, false),
+        ExecuteMsg::AddOperator { addr } => update_operator(deps, info, addr, true),
+        ExecuteMsg::RemoveOperator { addr } => update_operator(deps, info, addr, false),
+        ExecuteMsg::FreezeAccount { addr } => update_frozen(deps, info, addr, true),
+        ExecuteMsg::UnfreezeAccount { addr } => update_frozen(deps, info, addr, false),
+        ExecuteMsg::WithdrawFor { beneficiary, amount } => withdraw_for(deps, env, info, beneficiary, amount),
+        ExecuteMsg::UpdateFee { fee_numerator, fee_denominator, fee_bps } => {
+            let (fee_numerator, fee_denominator) = fee_ratio(fee_numerator, fee_denominator, fee_bps)?;
+            update_fee(deps, info, fee_numerator, fee_denominator)
+        }
+        ExecuteMsg::UpdateMinDeposit { min_deposit } => update_min_deposit(deps, info, min_deposit),
+        ExecuteMsg::UpdateFeeRecipient { recipient } => update_fee_recipient(deps, info, recipient),
+        ExecuteMsg::CreditBalance { recipient, amount } => credit_balance(deps, env, info, recipient, amount),
+        ExecuteMsg::DistributeBalance { recipients } => distribute_balance(deps, env, info, recipients),
+        ExecuteMsg::UpdateShares { shareholders } => update_shares(deps, env, info, shareholders),
+        ExecuteMsg::Revoke { recipient, amount } => revoke(deps, info, recipient, amount),
+        ExecuteMsg::SetTokenConfig { token, fee_numerator, fee_denominator, fee_bps, min_deposit, enabled } => {
+            let (fee_numerator, fee_denominator) = fee_ratio(fee_numerator, fee_denominator, fee_bps)?;
+            set_token_config(deps, info, token, TokenConfig {
+                fee_numerator,
+                fee_denominator,
+                min_deposit: min_deposit.unwrap_or_default(),
+                enabled,
+            })
+        }
+        ExecuteMsg::ResetFeeAccounting {} => reset_fee_accounting(deps, info),
     }
 }
 
-fn withdraw_all(
+fn transfer_ownership(
     deps: DepsMut,
     info: MessageInfo,
+    new_owner: String,
 ) -> Result<Response, ContractError> {
-    let amount = match WITHDRAWABLE.may_load(deps.storage, info.sender.clone())? {
-        Some(val) => val,
-        None => Uint128::zero()
-    };
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+    PENDING_OWNER.save(deps.storage, &new_owner)?;
 
-    return _withdraw(deps, info, amount);
+    Ok(Response::default())
 }
 
-fn withdraw_fee(
+fn accept_ownership(
     deps: DepsMut,
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
-    let contract_info = CONTRACT_INFO.load(deps.storage)?;
-    let token = contract_info.token;
+    // validate pending owner
+    match PENDING_OWNER.may_load(deps.storage)? {
+        Some(pending_owner) if pending_owner == info.sender => (),
+        _ => return Err(ContractError::Unauthorized {}),
+    }
+
+    CONTRACT_INFO.update(deps.storage, |mut contract_info| -> StdResult<_> {
+        contract_info.owner = info.sender;
+        Ok(contract_info)
+    })?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::default())
+}
+
+fn update_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+) -> Result<Response, ContractError> {
+    let mut contract_info = CONTRACT_INFO.load(deps.storage)?;
 
     // validate owner
     if contract_info.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
 
-    let fee = FEE_COLLECTED.load(deps.storage)?;
-    FEE_COLLECTED.save(deps.storage, &Uint128::zero())?;
+    let (token, token_info) = validate_token(deps.as_ref(), &token)?;
 
-    let msgs: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: token.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: info.sender.to_string(),
-            amount: fee,
-        })?,
-        funds: vec![],
-    })];
+    // the accounting of the current token must be settled before switching
+    if !load_fee_collected(deps.storage)?.is_zero() {
+        return Err(ContractError::OutstandingBalances {});
+    }
+    for item in WITHDRAWABLE.range(deps.storage, None, None, Order::Ascending) {
+        let (_, withdrawable) = item?;
+        if !withdrawable.is_zero() {
+            return Err(ContractError::OutstandingBalances {});
+        }
+    }
+    if LOCKED.keys(deps.storage, None, None, Order::Ascending).next().is_some()
+        || VESTING.keys(deps.storage, None, None, Order::Ascending).next().is_some()
+    {
+        return Err(ContractError::OutstandingBalances {});
+    }
+
+    // balances of the new token received while it was not the configured one move over
+    let balances = TOKEN_WITHDRAWABLE
+        .prefix(token.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+    for (addr, withdrawable) in balances {
+        TOKEN_WITHDRAWABLE.remove(deps.storage, (token.clone(), addr.clone()));
+        WITHDRAWABLE.save(deps.storage, addr, &withdrawable)?;
+        increase_total_withdrawable(deps.storage, withdrawable)?;
+    }
+    if let Some(fee_collected) = TOKEN_FEE_COLLECTED.may_load(deps.storage, token.clone())? {
+        TOKEN_FEE_COLLECTED.remove(deps.storage, token.clone());
+        FEE_COLLECTED.save(deps.storage, &fee_collected)?;
+    }
+
+    contract_info.token = token.clone();
+    contract_info.decimals = token_info.decimals;
+    contract_info.symbol = token_info.symbol;
+    CONTRACT_INFO.save(deps.storage, &contract_info)?;
 
-    Ok(Response::default().add_messages(msgs))
+    Ok(Response::default()
+        .add_attribute("action", "update_token")
+        .add_attribute("token", token))
 }
 
-fn _withdraw(
+fn update_depositor(
     deps: DepsMut,
     info: MessageInfo,
-    amount: Uint128,
+    addr: String,
+    allowed: bool,
 ) -> Result<Response, ContractError> {
     let contract_info = CONTRACT_INFO.load(deps.storage)?;
-    let token = contract_info.token;
 
-    if amount.is_zero() {
-        return Err(ContractError::Std(StdError::GenericErr {
-            msg: "Invalid zero amount".to_string(),
-        }));
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
     }
 
-    let withdrawable = match WITHDRAWABLE.may_load(deps.storage, info.sender.clone())? {
-        Some(val) => val,
-        None => Uint128::zero()
-    };
-    if amount > withdrawable {
-        return Err(ContractError::Std(StdError::GenericErr {
-            msg: "Insufficient amount".to_string(),
-        }));
+    let addr = deps.api.addr_validate(&addr)?;
+    if allowed {
+        DEPOSITORS.save(deps.storage, addr.clone(), &true)?;
+    } else {
+        DEPOSITORS.remove(deps.storage, addr.clone());
     }
 
-    WITHDRAWABLE.save(deps.storage, info.sender.clone(), &(withdrawable - amount))?;
+    Ok(Response::default()
+        .add_attribute("action", if allowed { "add_depositor" } else { "remove_depositor" })
+        .add_attribute("addr", addr))
+}
 
-    // Handle the real "withdraw"
-    let recipient = deps.api.addr_validate(info.sender.as_str())?;
-    let msgs: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: token.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: recipient.to_string(),
-            amount: amount,
-        })?,
-        funds: vec![],
-    })];
+fn update_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    set_fee(deps.storage, fee_numerator, fee_denominator)?;
 
-    Ok(Response::default().add_messages(msgs))
+    Ok(Response::default()
+        .add_attribute("action", "update_fee")
+        .add_attribute("fee_numerator", fee_numerator.to_string())
+        .add_attribute("fee_denominator", fee_denominator.to_string()))
 }
 
-fn deposit(
+fn update_min_deposit(
     deps: DepsMut,
     info: MessageInfo,
-    cw20_msg: Cw20ReceiveMsg,
+    min_deposit: Uint128,
+) -> Result<Response, ContractError> {
+    let mut contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    contract_info.min_deposit = min_deposit;
+    CONTRACT_INFO.save(deps.storage, &contract_info)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "update_min_deposit")
+        .add_attribute("min_deposit", min_deposit))
+}
+
+fn update_fee_recipient(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
 ) -> Result<Response, ContractError> {
-    let token_contract = info.sender;
-    let amount = cw20_msg.amount;
+    let mut contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    contract_info.fee_recipient = deps.api.addr_validate(&recipient)?;
+    CONTRACT_INFO.save(deps.storage, &contract_info)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "update_fee_recipient")
+        .add_attribute("recipient", contract_info.fee_recipient))
+}
 
+fn set_token_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+    token_config: TokenConfig,
+) -> Result<Response, ContractError> {
     let contract_info = CONTRACT_INFO.load(deps.storage)?;
 
-    // Deserialize the message for the params
-    match from_binary(&cw20_msg.msg) {
-        Ok(Cw20HookMsg::Deposit { addr1, addr2 }) => {
-            // Validations
-            if token_contract != contract_info.token {
-                return Err(ContractError::Std(StdError::GenericErr {
-                    msg: "Invalid token".to_string(),
-                }));
-            }
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
 
-            let fee = Uint128::from(amount.u128() * 50u128 / 1000u128);
-            let total_fee = FEE_COLLECTED.load(deps.storage)? + fee;
-            FEE_COLLECTED.save(deps.storage, &total_fee)?;
-            let send_amount = amount - fee;
+    validate_fee(token_config.fee_numerator, token_config.fee_denominator)?;
+    let (token, _) = validate_token(deps.as_ref(), &token)?;
+    TOKEN_CONFIGS.save(deps.storage, token.clone(), &token_config)?;
 
-            // Handle the real "deposit".
-            let amount1 = send_amount / Uint128::from(2u128);
-            let amount2 = send_amount - amount1;
+    Ok(Response::default()
+        .add_attribute("action", "set_token_config")
+        .add_attribute("token", token)
+        .add_attribute("enabled", token_config.enabled.to_string()))
+}
 
-            let withdrawable1 = match WITHDRAWABLE.may_load(deps.storage, deps.api.addr_validate(&addr1)?)? {
-                Some(val) => val,
-                None => Uint128::zero()
-            };
-            let withdrawable2 = match WITHDRAWABLE.may_load(deps.storage, deps.api.addr_validate(&addr2)?)? {
-                Some(val) => val,
-                None => Uint128::zero()
-            };
+fn update_shares(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    shareholders: Vec<(String, u64)>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // the new shares replace the previous ones
+    let previous = SHARES
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+    for addr in previous {
+        SHARES.remove(deps.storage, addr);
+    }
+    save_shares(deps.branch(), &env, &contract_info, shareholders)?;
 
-            WITHDRAWABLE.save(deps.storage, deps.api.addr_validate(&addr1)?, &(withdrawable1 + amount1))?;
-            WITHDRAWABLE.save(deps.storage, deps.api.addr_validate(&addr2)?, &(withdrawable2 + amount2))?;
+    Ok(Response::default().add_attribute("action", "update_shares"))
+}
 
-            Ok(Response::default())
+// Stores the weights of the shareholders, a zero weight is left out
+fn save_shares(
+    deps: DepsMut,
+    env: &Env,
+    contract_info: &ContractInfo,
+    shareholders: Vec<(String, u64)>,
+) -> Result<(), ContractError> {
+    let mut seen = BTreeSet::new();
+    for (addr, weight) in shareholders {
+        let addr = validate_recipient(deps.as_ref(), env, contract_info, &addr)?;
+        if !seen.insert(addr.clone()) {
+            return Err(ContractError::DuplicateShareholder { addr: addr.to_string() });
+        }
+        if weight > 0 {
+            SHARES.save(deps.storage, addr, &weight)?;
         }
-        Err(_) => Err(ContractError::Unauthorized {}),
     }
+
+    Ok(())
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Owner {} => to_binary(&get_owner(deps)?),
-        QueryMsg::Withdrawable { addr } => to_binary(&withdrawable(deps, addr)?),
+fn update_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    allowed: bool,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
     }
+
+    let addr = deps.api.addr_validate(&addr)?;
+    if allowed {
+        OPERATORS.save(deps.storage, addr.clone(), &true)?;
+    } else {
+        OPERATORS.remove(deps.storage, addr.clone());
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", if allowed { "add_operator" } else { "remove_operator" })
+        .add_attribute("addr", addr))
 }
 
-fn get_owner(deps: Deps) -> StdResult<String> {
+fn update_frozen(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    frozen: bool,
+) -> Result<Response, ContractError> {
     let contract_info = CONTRACT_INFO.load(deps.storage)?;
 
-    Ok(contract_info.owner.to_string())
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let addr = deps.api.addr_validate(&addr)?;
+    if frozen {
+        FROZEN.save(deps.storage, addr.clone(), &true)?;
+    } else {
+        FROZEN.remove(deps.storage, addr.clone());
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", if frozen { "freeze_account" } else { "unfreeze_account" })
+        .add_attribute("addr", addr))
 }
 
-fn withdrawable(deps: Deps, addr: String) -> StdResult<Uint128> {
-    match WITHDRAWABLE.may_load(deps.storage, deps.api.addr_validate(&addr)?)? {
-        Some(val) => Ok(val),
-        None => Ok(Uint128::zero())
+// Frozen accounts cannot withdraw any of their balances
+fn check_not_frozen(storage: &dyn Storage, account: &Addr) -> Result<(), ContractError> {
+    if FROZEN.has(storage, account.clone()) {
+        return Err(ContractError::AccountFrozen {});
+    }
+
+    Ok(())
+}
+
+// Withdraws on behalf of a beneficiary, the tokens always go to the beneficiary
+fn withdraw_for(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    beneficiary: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner or operator
+    if contract_info.owner != info.sender
+        && !OPERATORS.may_load(deps.storage, info.sender.clone())?.unwrap_or(false)
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let beneficiary = deps.api.addr_validate(&beneficiary)?;
+
+    Ok(_withdraw(deps, env, beneficiary, amount, None, None)?.add_attribute("operator", info.sender))
+}
+
+fn withdraw_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    check_not_frozen(deps.storage, &info.sender)?;
+    release_unlocked(deps.storage, &env, &info.sender)?;
+
+    // the balance is read once and emptied, no need to go through debit
+    let amount = match WITHDRAWABLE.may_load(deps.storage, info.sender.clone())? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+    if amount.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+    let last_withdrawal = check_cooldown(deps.storage, &contract_info, &env, &info.sender)?;
+
+    WITHDRAWABLE.save(deps.storage, info.sender.clone(), &Uint128::zero())?;
+    decrease_total_withdrawable(deps.storage, amount)?;
+    check_contract_balance(deps.as_ref(), &env, &contract_info, amount - withdraw_fee_of(&contract_info, amount))?;
+
+    let withdrawal = WithdrawResponse { withdrawn: amount, remaining: Uint128::zero() };
+    send_withdrawal(deps, &contract_info, info.sender, None, None, withdrawal, last_withdrawal)
+}
+
+fn withdraw_percent(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bps: u16,
+) -> Result<Response, ContractError> {
+    // Validations
+    if bps > BPS_DENOMINATOR {
+        return Err(ContractError::InvalidPercent {});
+    }
+
+    // the percent applies to the balance including what can already be released
+    release_unlocked(deps.storage, &env, &info.sender)?;
+    let withdrawable = WITHDRAWABLE.may_load(deps.storage, info.sender.clone())?.unwrap_or_default();
+    let amount = withdrawable.multiply_ratio(bps, BPS_DENOMINATOR);
+
+    _withdraw(deps, env, info.sender, amount, None, None)
+}
+
+fn withdraw_fee(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Option<String>,
+    amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let token = contract_info.token.clone();
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // fees go to the fee recipient unless another one is given
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => contract_info.fee_recipient.clone(),
+    };
+
+    // sweep everything unless a partial amount is requested
+    let fee_collected = load_fee_collected(deps.storage)?;
+    // forwarded deposit fees never accrue, but the withdraw fee and revoked vestings without
+    // a recovery address still do
+    if let FeeMode::Forward { .. } = contract_info.fee_mode {
+        if fee_collected.is_zero() {
+            return Err(ContractError::FeeModeIsForward {});
+        }
+    }
+    let fee = amount.unwrap_or(fee_collected);
+    if fee > fee_collected {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Insufficient fee".to_string(),
+        }));
+    }
+    check_contract_balance(deps.as_ref(), &env, &contract_info, fee)?;
+    let remaining = fee_collected - fee;
+    FEE_COLLECTED.save(deps.storage, &remaining)?;
+
+    // burned fees are not sent anywhere, the recipient is ignored
+    if contract_info.burn_fee {
+        let msg = SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn { amount: fee })?,
+                funds: vec![],
+            }),
+            BURN_FEE_REPLY_ID,
+        );
+
+        return Ok(Response::default()
+            .add_submessage(msg)
+            .add_attribute("action", "burn_fee")
+            .add_attribute("amount", fee)
+            .add_attribute("remaining", remaining));
+    }
+
+    let msgs: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount: fee,
+        })?,
+        funds: vec![],
+    })];
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "withdraw_fee")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", fee)
+        .add_attribute("remaining", remaining))
+}
+
+// Sends the owner any balance of the configured token that is not owed to anyone,
+// e.g. tokens transferred directly instead of through Receive
+fn sweep_unallocated(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let surplus = unallocated(deps.as_ref(), env)?;
+    if surplus.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
     }
+
+    let msgs: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract_info.token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: info.sender.to_string(),
+            amount: surplus,
+        })?,
+        funds: vec![],
+    })];
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "sweep_unallocated")
+        .add_attribute("amount", surplus))
+}
+
+// Credits a recipient out of the balance of the configured token that is not owed to anyone,
+// e.g. tokens transferred to the contract to bootstrap initial balances
+fn credit_balance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if amount.is_zero() {
+        return Err(ContractError::ZeroAmount {});
+    }
+
+    let available = unallocated(deps.as_ref(), env.clone())?;
+    if amount > available {
+        return Err(ContractError::InsufficientFunds {
+            requested: amount,
+            available,
+        });
+    }
+
+    let recipient = validate_recipient(deps.as_ref(), &env, &contract_info, &recipient)?;
+    check_cap(deps.as_ref(), &contract_info, amount)?;
+    credit(deps.storage, &contract_info, &contract_info.token, &recipient, amount)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "credit_balance")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount))
+}
+
+// Credits everything the contract holds beyond what it owes to the recipients, the last one
+// receives the rounding remainder unless it joins the fees
+fn distribute_balance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipients: Vec<String>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if recipients.is_empty() {
+        return Err(ContractError::EmptyRecipients {});
+    }
+    check_recipients(&contract_info, recipients.len())?;
+    let recipients = recipients
+        .iter()
+        .map(|addr| validate_recipient(deps.as_ref(), &env, &contract_info, addr))
+        .collect::<Result<Vec<Addr>, ContractError>>()?;
+
+    let surplus = unallocated(deps.as_ref(), env)?;
+    if surplus.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+
+    let share = surplus / Uint128::from(recipients.len() as u128);
+    let dust = pooled_dust(contract_info.dust_policy, None, Uint128::zero(), surplus - share * Uint128::from(recipients.len() as u128));
+    check_cap(deps.as_ref(), &contract_info, surplus - dust)?;
+    add_fee_collected(deps.storage, dust)?;
+    let (last, rest) = recipients.split_last().unwrap();
+    for recipient in rest {
+        credit(deps.storage, &contract_info, &contract_info.token, recipient, share)?;
+    }
+    credit(deps.storage, &contract_info, &contract_info.token, last, surplus - dust - share * Uint128::from(rest.len() as u128))?;
+
+    Ok(Response::default()
+        .add_attribute("action", "distribute_balance")
+        .add_attribute("amount", surplus)
+        .add_attribute("recipients", recipients.len().to_string()))
+}
+
+// Takes back part of the withdrawable balance of a recipient credited by mistake, locked and
+// vesting amounts cannot be revoked. It goes to the recovery address, or to the fees without one.
+fn revoke(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let remaining = debit(deps.storage, &WITHDRAWABLE, recipient.clone(), amount)?;
+    decrease_total_withdrawable(deps.storage, amount)?;
+
+    // the revoked amount was never earned
+    TOTAL_EARNED.update(deps.storage, recipient.clone(), |total_earned| -> StdResult<_> {
+        Ok(total_earned.unwrap_or_default().saturating_sub(amount))
+    })?;
+
+    let res = Response::default()
+        .add_attribute("action", "revoke")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount)
+        .add_attribute("remaining", remaining);
+
+    match contract_info.recovery_addr.clone() {
+        Some(recovery_addr) => {
+            credit(deps.storage, &contract_info, &contract_info.token, &recovery_addr, amount)?;
+            Ok(res.add_attribute("recovered_to", recovery_addr))
+        }
+        None => {
+            add_fee_collected(deps.storage, amount)?;
+            Ok(res.add_attribute("recovered_to", "fee"))
+        }
+    }
+}
+
+fn reset_fee_accounting(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let fee_collected = load_fee_collected(deps.storage)?;
+    FEE_COLLECTED.save(deps.storage, &Uint128::zero())?;
+
+    Ok(Response::default()
+        .add_attribute("action", "reset_fee_accounting")
+        .add_attribute("previous", fee_collected))
+}
+
+// Balance of the configured token held by the contract beyond what it owes
+fn unallocated(deps: Deps, env: Env) -> StdResult<Uint128> {
+    let balance = token_balance(deps, env)?;
+
+    let allocated = load_fee_collected(deps.storage)?.checked_add(total_withdrawable(deps)?)?;
+
+    // accounting may exceed the real balance, nothing is unallocated then
+    Ok(balance.saturating_sub(allocated))
+}
+
+fn withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    recipient: Option<String>,
+    forward_contract: Option<String>,
+    forward_msg: Option<Binary>,
+) -> Result<Response, ContractError> {
+    // a forwarded withdrawal is sent to the contract along with its hook, instead of the recipient
+    let recipient = match (recipient, forward_contract, &forward_msg) {
+        (recipient, None, None) => recipient,
+        (None, Some(forward_contract), Some(_)) => Some(forward_contract),
+        _ => {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Invalid forward".to_string(),
+            }))
+        }
+    };
+
+    _withdraw(deps, env, info.sender, amount, recipient, forward_msg)
+}
+
+fn _withdraw(
+    deps: DepsMut,
+    env: Env,
+    account: Addr,
+    amount: Uint128,
+    recipient: Option<String>,
+    forward_msg: Option<Binary>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    check_not_frozen(deps.storage, &account)?;
+    let last_withdrawal = check_cooldown(deps.storage, &contract_info, &env, &account)?;
+
+    release_unlocked(deps.storage, &env, &account)?;
+
+    let remaining = debit(deps.storage, &WITHDRAWABLE, account.clone(), amount)?;
+    decrease_total_withdrawable(deps.storage, amount)?;
+    check_contract_balance(deps.as_ref(), &env, &contract_info, amount - withdraw_fee_of(&contract_info, amount))?;
+
+    let withdrawal = WithdrawResponse { withdrawn: amount, remaining };
+    send_withdrawal(deps, &contract_info, account, recipient, forward_msg, withdrawal, last_withdrawal)
+}
+
+// Fails before the transfer is emitted when the contract holds less than it would send, instead
+// of the opaque error of the token contract. Costs a query, so only done when configured.
+fn check_contract_balance(deps: Deps, env: &Env, contract_info: &ContractInfo, sent: Uint128) -> Result<(), ContractError> {
+    if !contract_info.check_contract_balance {
+        return Ok(());
+    }
+
+    if token_balance(deps, env.clone())? < sent {
+        return Err(ContractError::InsufficientContractBalance {});
+    }
+
+    Ok(())
+}
+
+// Records the withdrawal time of the account, failing if its previous one is too recent. Returns
+// the previous time, which is restored if the transfer of the withdrawal fails.
+fn check_cooldown(
+    storage: &mut dyn Storage,
+    contract_info: &ContractInfo,
+    env: &Env,
+    account: &Addr,
+) -> Result<Option<Timestamp>, ContractError> {
+    let last_withdrawal = check_cooldown_elapsed(storage, contract_info, env, account)?;
+    if contract_info.withdraw_cooldown > 0 {
+        LAST_WITHDRAWAL.save(storage, account.clone(), &env.block.time)?;
+    }
+
+    Ok(last_withdrawal)
+}
+
+// Fails if the previous withdrawal of the account is too recent, without recording a new one
+fn check_cooldown_elapsed(
+    storage: &dyn Storage,
+    contract_info: &ContractInfo,
+    env: &Env,
+    account: &Addr,
+) -> Result<Option<Timestamp>, ContractError> {
+    let last_withdrawal = LAST_WITHDRAWAL.may_load(storage, account.clone())?;
+    if contract_info.withdraw_cooldown == 0 {
+        return Ok(last_withdrawal);
+    }
+
+    if let Some(last) = last_withdrawal {
+        if env.block.time < last.plus_seconds(contract_info.withdraw_cooldown) {
+            return Err(ContractError::CooldownActive {});
+        }
+    }
+
+    Ok(last_withdrawal)
+}
+
+// Pays out an amount of the configured token already debited from the account, minus the withdrawal fee
+fn send_withdrawal(
+    deps: DepsMut,
+    contract_info: &ContractInfo,
+    account: Addr,
+    recipient: Option<String>,
+    forward_msg: Option<Binary>,
+    withdrawal: WithdrawResponse,
+    last_withdrawal: Option<Timestamp>,
+) -> Result<Response, ContractError> {
+    let data = to_binary(&withdrawal)?;
+    let WithdrawResponse { withdrawn: amount, remaining } = withdrawal;
+    let fee = withdraw_fee_of(contract_info, amount);
+    if !fee.is_zero() {
+        add_fee_collected(deps.storage, fee)?;
+    }
+    add_stat(deps.storage, &TOTAL_WITHDRAWN, amount - fee)?;
+    add_stat(deps.storage, &TOTAL_FEES, fee)?;
+
+    // Handle the real "withdraw", to the account unless another recipient is given
+    let recipient = deps.api.addr_validate(recipient.as_deref().unwrap_or(account.as_str()))?;
+    let pending = PendingWithdrawal {
+        token: contract_info.token.clone(),
+        account: account.clone(),
+        amount: amount - fee,
+        fee,
+        last_withdrawal,
+    };
+    let msg = transfer_with_reply(deps.storage, pending, &recipient, forward_msg)?;
+
+    let mut res = Response::default()
+        .add_submessage(msg)
+        .set_data(data)
+        .add_attribute("action", "withdraw")
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("remaining", remaining);
+    if !fee.is_zero() {
+        res = res.add_attribute("withdraw_fee", fee);
+    }
+
+    Ok(res
+        .add_event(
+            Event::new("token_distribution")
+                .add_attribute("action", "withdraw")
+                .add_attribute("account", account)
+                .add_attribute("recipient", recipient)
+                .add_attribute("amount", amount),
+        ))
+}
+
+// Fee kept on a withdrawal of the configured token
+fn withdraw_fee_of(contract_info: &ContractInfo, amount: Uint128) -> Uint128 {
+    amount.multiply_ratio(contract_info.withdraw_fee_bps, BPS_DENOMINATOR)
+}
+
+// Builds the cw20 transfer of a withdrawal, or a Send when a hook is forwarded to the recipient
+// contract. It replies on completion so that a failing transfer credits the debited amount back
+// instead of aborting the whole transaction, the reply is also requested on success to clear the
+// pending entry.
+fn transfer_with_reply(
+    storage: &mut dyn Storage,
+    pending: PendingWithdrawal,
+    recipient: &Addr,
+    forward_msg: Option<Binary>,
+) -> StdResult<SubMsg> {
+    let id = LAST_REPLY_ID.may_load(storage)?.unwrap_or_default() + 1;
+    LAST_REPLY_ID.save(storage, &id)?;
+    PENDING_WITHDRAWALS.save(storage, id, &pending)?;
+    let PendingWithdrawal { token, amount, .. } = pending;
+
+    let msg = match forward_msg {
+        Some(msg) => Cw20ExecuteMsg::Send {
+            contract: recipient.to_string(),
+            amount,
+            msg,
+        },
+        None => Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        },
+    };
+
+    Ok(SubMsg::reply_always(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        }),
+        id,
+    ))
+}
+
+// Debits a withdrawable balance and returns what is left
+fn debit<'a, K: PrimaryKey<'a>>(
+    storage: &mut dyn Storage,
+    balances: &Map<'a, K, Uint128>,
+    key: K,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::ZeroAmount {});
+    }
+
+    let withdrawable = match balances.may_load(storage, key.clone())? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+    if amount > withdrawable {
+        return Err(ContractError::InsufficientFunds {
+            requested: amount,
+            available: withdrawable,
+        });
+    }
+
+    let remaining = withdrawable - amount;
+    balances.save(storage, key, &remaining)?;
+
+    Ok(remaining)
+}
+
+fn withdraw_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    check_not_frozen(deps.storage, &info.sender)?;
+    let token = deps.api.addr_validate(&token)?;
+
+    let remaining = debit(deps.storage, &TOKEN_WITHDRAWABLE, (token.clone(), info.sender.clone()), amount)?;
+
+    let recipient = deps.api.addr_validate(recipient.as_deref().unwrap_or(info.sender.as_str()))?;
+    let pending = PendingWithdrawal {
+        token: token.clone(),
+        account: info.sender.clone(),
+        amount,
+        fee: Uint128::zero(),
+        last_withdrawal: None,
+    };
+    let msg = transfer_with_reply(deps.storage, pending, &recipient, None)?;
+
+    Ok(Response::default()
+        .add_submessage(msg)
+        .add_attribute("action", "withdraw_token")
+        .add_attribute("token", token)
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount)
+        .add_attribute("remaining", remaining))
+}
+
+fn withdraw_token_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let token = deps.api.addr_validate(&token)?;
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => contract_info.fee_recipient.clone(),
+    };
+
+    let fee = match TOKEN_FEE_COLLECTED.may_load(deps.storage, token.clone())? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+    if fee.is_zero() {
+        return Err(ContractError::ZeroAmount {});
+    }
+    TOKEN_FEE_COLLECTED.save(deps.storage, token.clone(), &Uint128::zero())?;
+
+    let msgs: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount: fee,
+        })?,
+        funds: vec![],
+    })];
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "withdraw_token_fee")
+        .add_attribute("token", token)
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", fee))
+}
+
+fn deposit(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    // any cw20 can be distributed, balances are kept per token
+    let token = info.sender;
+
+    let contract_info = token_settings(deps.storage, CONTRACT_INFO.load(deps.storage)?, &token)?;
+
+    // a token levying its own transfer fee delivers less than announced. The hook runs after the
+    // transfer, so the balance before it is unknown: the received amount is taken as the balance
+    // the contract does not owe yet, capped by the announced one. A surplus already held, e.g. a
+    // direct transfer, is counted as received up to that cap and should be swept beforehand.
+    // Withdrawals debit and send the full amount, the recipient bears the fee of the transfer out.
+    let deflationary = contract_info.deflationary_token && token == contract_info.token;
+    let amount = if deflationary {
+        cw20_msg.amount.min(unallocated(deps.as_ref(), env.clone())?)
+    } else {
+        cw20_msg.amount
+    };
+
+    // Validations
+    if amount < contract_info.min_deposit {
+        return Err(ContractError::DepositTooSmall {});
+    }
+    // an empty allowlist lets anyone deposit
+    let depositor = deps.api.addr_validate(&cw20_msg.sender)?;
+    if !is_allowed_depositor(deps.storage, &depositor)? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let deposit_id = DEPOSIT_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+
+    // Deserialize the message for the params
+    let (res, fee) = match from_binary(&cw20_msg.msg) {
+        Ok(Cw20HookMsg::Deposit(msg)) if msg.addr1.is_none() && msg.addr2.is_none() => {
+            // Validations
+            check_deadline(&env, msg.deadline)?;
+            if msg.unlock_time.is_some() || msg.vesting.is_some() {
+                return Err(ContractError::Std(StdError::GenericErr {
+                    msg: "Release schedule not supported".to_string(),
+                }));
+            }
+            use_nonce(deps.storage, &depositor, msg.nonce.as_deref())?;
+
+            // without recipients the deposit is split by the members of the group when one is
+            // configured, by the shares otherwise
+            let (action, recipients) = match &contract_info.group {
+                Some(group) => {
+                    let recipients = group_members(deps.as_ref(), &contract_info, group)?
+                        .iter()
+                        .map(|(addr, weight)| Ok((validate_recipient(deps.as_ref(), &env, &contract_info, addr)?, *weight)))
+                        .collect::<Result<Vec<(Addr, u64)>, ContractError>>()?;
+                    ("deposit_group", recipients)
+                }
+                None => {
+                    let recipients = SHARES
+                        .range(deps.storage, None, None, Order::Ascending)
+                        .collect::<StdResult<Vec<(Addr, u64)>>>()?;
+                    check_recipients(&contract_info, recipients.len())?;
+                    ("deposit_shares", recipients)
+                }
+            };
+            if recipients.is_empty() {
+                return Err(ContractError::NoRecipients {});
+            }
+
+            let send_amount = deposit_weighted(
+                deps.storage,
+                &contract_info,
+                &token,
+                deposit_id,
+                amount,
+                &recipients,
+                msg.min_recipient_amount,
+            )?;
+
+            let res = Response::default()
+                .add_attribute("action", action)
+                .add_attribute("amount", amount)
+                .add_attribute("fee", amount - send_amount);
+            (res, amount - send_amount)
+        }
+        Ok(Cw20HookMsg::Deposit(msg)) => {
+            use_nonce(deps.storage, &depositor, msg.nonce.as_deref())?;
+            let mut credits = BTreeMap::new();
+            let pair = deposit_pair(deps.branch(), &env, &contract_info, &token, amount, msg, &mut credits)?;
+            flush_credits(deps.storage, &contract_info, &token, credits)?;
+            index_deposit(deps.storage, deposit_id, &pair.addr1, pair.amount1)?;
+            index_deposit(deps.storage, deposit_id, &pair.addr2, pair.amount2)?;
+
+            let res = Response::default()
+                .add_attribute("action", "deposit")
+                .add_attribute("amount", amount)
+                .add_attribute("fee", pair.fee)
+                .add_attribute("addr1", pair.addr1)
+                .add_attribute("amount1", pair.amount1)
+                .add_attribute("addr2", pair.addr2)
+                .add_attribute("amount2", pair.amount2);
+            (res, pair.fee)
+        }
+        Ok(Cw20HookMsg::BatchDeposit { deposits }) => {
+            // Validations
+            // the items sum to the announced amount, not to what a deflationary token delivered
+            if deflationary {
+                return Err(ContractError::DeflationaryExactAmounts {});
+            }
+            // every entry credits two recipients
+            check_recipients(&contract_info, deposits.len() * 2)?;
+            let total = deposits
+                .iter()
+                .try_fold(Uint128::zero(), |total, item| total.checked_add(item.amount).map_err(StdError::from))?;
+            if total != amount {
+                return Err(ContractError::BatchAmountMismatch { expected: amount, actual: total });
+            }
+
+            // every entry pays its own fee and is split on its own, a recipient of several entries
+            // is credited their sum with a single write
+            let mut fee = Uint128::zero();
+            let mut credits = BTreeMap::new();
+            for item in deposits {
+                use_nonce(deps.storage, &depositor, item.deposit.nonce.as_deref())?;
+                let pair = deposit_pair(deps.branch(), &env, &contract_info, &token, item.amount, item.deposit, &mut credits)?;
+                index_deposit(deps.storage, deposit_id, &pair.addr1, pair.amount1)?;
+                index_deposit(deps.storage, deposit_id, &pair.addr2, pair.amount2)?;
+                fee += pair.fee;
+            }
+            flush_credits(deps.storage, &contract_info, &token, credits)?;
+
+            let res = Response::default()
+                .add_attribute("action", "batch_deposit")
+                .add_attribute("amount", amount)
+                .add_attribute("fee", fee);
+            (res, fee)
+        }
+        Ok(Cw20HookMsg::DepositMany { recipients }) => {
+            // Validations
+            if recipients.is_empty() {
+                return Err(ContractError::EmptyRecipients {});
+            }
+            check_recipients(&contract_info, recipients.len())?;
+            let recipients = recipients
+                .iter()
+                .map(|addr| validate_recipient(deps.as_ref(), &env, &contract_info, addr))
+                .collect::<Result<Vec<Addr>, ContractError>>()?;
+
+            let mut send_amount = take_fee(deps.storage, &contract_info, &token, amount)?;
+
+            // Split evenly, the last recipient receives the rounding remainder unless it joins the fee
+            let share = send_amount / Uint128::from(recipients.len() as u128);
+            let dust = send_amount - share * Uint128::from(recipients.len() as u128);
+            send_amount -= pool_dust(deps.storage, &contract_info, &token, amount - send_amount, dust)?;
+            let (last, rest) = recipients.split_last().unwrap();
+            for recipient in rest {
+                credit(deps.storage, &contract_info, &token, recipient, share)?;
+                index_deposit(deps.storage, deposit_id, recipient, share)?;
+            }
+            let last_share = send_amount - share * Uint128::from(rest.len() as u128);
+            credit(deps.storage, &contract_info, &token, last, last_share)?;
+            index_deposit(deps.storage, deposit_id, last, last_share)?;
+
+            let res = Response::default()
+                .add_attribute("action", "deposit_many")
+                .add_attribute("amount", amount)
+                .add_attribute("fee", amount - send_amount);
+            (res, amount - send_amount)
+        }
+        Ok(Cw20HookMsg::DepositWeighted { recipients }) => {
+            check_recipients(&contract_info, recipients.len())?;
+            let recipients = recipients
+                .iter()
+                .map(|(addr, weight)| Ok((validate_recipient(deps.as_ref(), &env, &contract_info, addr)?, *weight)))
+                .collect::<Result<Vec<(Addr, u64)>, ContractError>>()?;
+
+            let send_amount = deposit_weighted(deps.storage, &contract_info, &token, deposit_id, amount, &recipients, None)?;
+
+            let res = Response::default()
+                .add_attribute("action", "deposit_weighted")
+                .add_attribute("amount", amount)
+                .add_attribute("fee", amount - send_amount);
+            (res, amount - send_amount)
+        }
+        Ok(Cw20HookMsg::DepositExact { recipients }) => {
+            // Validations
+            if deflationary {
+                return Err(ContractError::DeflationaryExactAmounts {});
+            }
+            if recipients.is_empty() {
+                return Err(ContractError::EmptyRecipients {});
+            }
+            check_recipients(&contract_info, recipients.len())?;
+            let recipients = recipients
+                .iter()
+                .map(|(addr, amount)| Ok((validate_recipient(deps.as_ref(), &env, &contract_info, addr)?, *amount)))
+                .collect::<Result<Vec<(Addr, Uint128)>, ContractError>>()?;
+            let total = recipients
+                .iter()
+                .try_fold(Uint128::zero(), |total, (_, amount)| total.checked_add(*amount).map_err(StdError::from))?;
+
+            let send_amount = take_fee(deps.storage, &contract_info, &token, amount)?;
+            if total != send_amount {
+                return Err(ContractError::ExactAmountMismatch { expected: send_amount, actual: total });
+            }
+
+            for (recipient, share) in recipients {
+                credit(deps.storage, &contract_info, &token, &recipient, share)?;
+                index_deposit(deps.storage, deposit_id, &recipient, share)?;
+            }
+
+            let res = Response::default()
+                .add_attribute("action", "deposit_exact")
+                .add_attribute("amount", amount)
+                .add_attribute("fee", amount - send_amount);
+            (res, amount - send_amount)
+        }
+        Err(_) => return Err(ContractError::InvalidHookMsg {}),
+    };
+
+    // the credits above are reverted if they pushed the outstanding total over the cap
+    if token == contract_info.token {
+        check_cap(deps.as_ref(), &contract_info, Uint128::zero())?;
+    }
+
+    DEPOSIT_COUNT.save(deps.storage, &deposit_id)?;
+    if token == contract_info.token {
+        add_stat(deps.storage, &TOTAL_DEPOSITED, amount)?;
+        add_stat(deps.storage, &TOTAL_FEES, fee)?;
+    }
+    DEPOSITS.save(
+        deps.storage,
+        deposit_id,
+        &DepositRecord {
+            sender: depositor,
+            token: token.clone(),
+            amount,
+            fee,
+            time: env.block.time,
+        },
+    )?;
+    let res = res.add_attribute("deposit_id", deposit_id.to_string());
+
+    // forwarded fees are not accrued by take_fee
+    match contract_info.fee_mode {
+        FeeMode::Forward { recipient } if !fee.is_zero() => Ok(res.add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient, amount: fee })?,
+            funds: vec![],
+        }))),
+        _ => Ok(res),
+    }
+}
+
+// Takes the fee and splits the rest by weight, returns the amount sent to the recipients
+fn deposit_weighted(
+    storage: &mut dyn Storage,
+    contract_info: &ContractInfo,
+    token: &Addr,
+    deposit_id: u64,
+    amount: Uint128,
+    recipients: &[(Addr, u64)],
+    min_recipient_amount: Option<Uint128>,
+) -> Result<Uint128, ContractError> {
+    // Validations
+    let total_weight: u128 = recipients.iter().map(|(_, weight)| *weight as u128).sum();
+    if total_weight == 0 {
+        return Err(ContractError::ZeroTotalWeight {});
+    }
+
+    let send_amount = take_fee(storage, contract_info, token, amount)?;
+    let (shares, dust) = weighted_amounts(contract_info, token, amount - send_amount, send_amount, recipients, total_weight);
+    accrue_fee(storage, contract_info, token, dust)?;
+    if let Some(min) = min_recipient_amount {
+        if shares.iter().any(|share| *share < min) {
+            return Err(ContractError::SlippageExceeded {});
+        }
+    }
+
+    for ((recipient, _), share) in recipients.iter().zip(shares) {
+        credit(storage, contract_info, token, recipient, share)?;
+        index_deposit(storage, deposit_id, recipient, share)?;
+    }
+
+    Ok(send_amount - dust)
+}
+
+// Members of a cw4 group with a non-zero weight, read page by page until an empty one, as a group
+// may return fewer than the limit before the end. Paging stops as soon as there are more than
+// max_recipients of them, or once more than GROUP_FETCH_FACTOR times that many were read at all
+// so that a group of mostly zero weights cannot make the paging unbounded.
+fn group_members(deps: Deps, contract_info: &ContractInfo, group: &Addr) -> Result<Vec<(String, u64)>, ContractError> {
+    let mut members = vec![];
+    let mut fetched = 0usize;
+    let mut start_after = None;
+    loop {
+        let page: MemberListResponse = deps.querier.query_wasm_smart(
+            group.to_string(),
+            &Cw4QueryMsg::ListMembers {
+                start_after,
+                limit: Some(MAX_LIMIT),
+            },
+        )?;
+        let last = match page.members.last() {
+            Some(member) => member.addr.clone(),
+            None => break,
+        };
+        fetched += page.members.len();
+        if fetched > contract_info.max_recipients as usize * GROUP_FETCH_FACTOR {
+            return Err(ContractError::TooManyRecipients {});
+        }
+        members.extend(
+            page.members
+                .into_iter()
+                .filter(|member| member.weight > 0)
+                .map(|member| (member.addr, member.weight)),
+        );
+        check_recipients(contract_info, members.len())?;
+        start_after = Some(last);
+    }
+
+    Ok(members)
+}
+
+// Adds the amount a deposit credited to a recipient to its history, an address credited twice
+// by the same deposit has a single entry
+fn index_deposit(storage: &mut dyn Storage, deposit_id: u64, recipient: &Addr, amount: Uint128) -> StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+
+    RECIPIENT_DEPOSITS.update(storage, (recipient.clone(), deposit_id), |credited| -> StdResult<_> {
+        Ok(credited.unwrap_or_default() + amount)
+    })?;
+
+    Ok(())
+}
+
+// Splits what is left of a deposit after its fee by weight, the first highest-weight recipient
+// receives the rounding remainder but for the part pooled with the fee, returned along
+fn weighted_amounts(
+    contract_info: &ContractInfo,
+    token: &Addr,
+    fee: Uint128,
+    amount: Uint128,
+    recipients: &[(Addr, u64)],
+    total_weight: u128,
+) -> (Vec<Uint128>, Uint128) {
+    let mut amounts: Vec<Uint128> = recipients
+        .iter()
+        .map(|(_, weight)| amount.multiply_ratio(*weight, total_weight))
+        .collect();
+    let mut top = 0;
+    for (i, (_, weight)) in recipients.iter().enumerate() {
+        if *weight > recipients[top].1 {
+            top = i;
+        }
+    }
+    let remainder = amount - amounts.iter().sum::<Uint128>();
+    let dust = pooled_dust(contract_info.dust_policy, max_fee_of(contract_info, token), fee, remainder);
+    if let Some(share) = amounts.get_mut(top) {
+        *share += remainder - dust;
+    }
+
+    (amounts, dust)
+}
+
+// Applies the fee and minimum deposit configured for the token. Once a token is configured, only the
+// configured token of the contract and enabled tokens can be deposited.
+fn token_settings(
+    storage: &dyn Storage,
+    mut contract_info: ContractInfo,
+    token: &Addr,
+) -> Result<ContractInfo, ContractError> {
+    match TOKEN_CONFIGS.may_load(storage, token.clone())? {
+        Some(token_config) => {
+            if !token_config.enabled {
+                return Err(ContractError::TokenNotAccepted { addr: token.to_string() });
+            }
+            contract_info.fee_numerator = token_config.fee_numerator;
+            contract_info.fee_denominator = token_config.fee_denominator;
+            contract_info.min_deposit = token_config.min_deposit;
+        }
+        None if *token != contract_info.token => {
+            if TOKEN_CONFIGS.keys(storage, None, None, Order::Ascending).next().is_some() {
+                return Err(ContractError::TokenNotAccepted { addr: token.to_string() });
+            }
+            // the minimum deposit is only set for the configured token
+            contract_info.min_deposit = Uint128::zero();
+        }
+        None => (),
+    }
+
+    Ok(contract_info)
+}
+
+fn is_allowed_depositor(storage: &dyn Storage, depositor: &Addr) -> StdResult<bool> {
+    if DEPOSITORS.may_load(storage, depositor.clone())?.unwrap_or(false) {
+        return Ok(true);
+    }
+
+    Ok(DEPOSITORS
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_none())
+}
+
+// Amounts credited by a two-recipient deposit
+struct PairDeposit {
+    fee: Uint128,
+    addr1: Addr,
+    amount1: Uint128,
+    addr2: Addr,
+    amount2: Uint128,
+}
+
+fn deposit_pair(
+    deps: DepsMut,
+    env: &Env,
+    contract_info: &ContractInfo,
+    token: &Addr,
+    amount: Uint128,
+    msg: DepositMsg,
+    credits: &mut BTreeMap<Addr, Uint128>,
+) -> Result<PairDeposit, ContractError> {
+    // Validations
+    check_deadline(env, msg.deadline)?;
+    let addr1 = check_not_empty("addr1", msg.addr1.as_deref())?;
+    let addr2 = check_not_empty("addr2", msg.addr2.as_deref())?;
+    let addr1 = validate_recipient(deps.as_ref(), env, contract_info, addr1)?;
+    let addr2 = validate_recipient(deps.as_ref(), env, contract_info, addr2)?;
+    if (msg.unlock_time.is_some() || msg.vesting.is_some()) && *token != contract_info.token {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Release schedule not supported".to_string(),
+        }));
+    }
+    if let Some(schedule) = &msg.vesting {
+        if schedule.end <= schedule.start {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Invalid vesting schedule".to_string(),
+            }));
+        }
+    }
+
+    let (fee, amount1, amount2) = deposit_split(contract_info, token, amount)?;
+    check_slippage(msg.min_recipient_amount, amount1, amount2)?;
+    accrue_fee(deps.storage, contract_info, token, fee)?;
+
+    // Credit one after the other so that addr1 == addr2 accumulates both amounts, immediate
+    // credits are gathered and written by the caller
+    match (msg.unlock_time, msg.vesting) {
+        (Some(_), Some(_)) => {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Invalid vesting schedule".to_string(),
+            }))
+        }
+        (None, Some(schedule)) => {
+            vest(deps.storage, &addr1, &schedule, amount1)?;
+            vest(deps.storage, &addr2, &schedule, amount2)?;
+        }
+        (Some(unlock_time), None) if unlock_time > env.block.time => {
+            lock(deps.storage, &addr1, unlock_time, amount1)?;
+            lock(deps.storage, &addr2, unlock_time, amount2)?;
+        }
+        _ => {
+            *credits.entry(addr1.clone()).or_default() += amount1;
+            *credits.entry(addr2.clone()).or_default() += amount2;
+        }
+    }
+
+    Ok(PairDeposit {
+        fee,
+        addr1,
+        amount1,
+        addr2,
+        amount2,
+    })
+}
+
+// Fails if a recipient would be credited less than the depositor expected, e.g. after a fee update
+fn check_slippage(min_recipient_amount: Option<Uint128>, amount1: Uint128, amount2: Uint128) -> Result<(), ContractError> {
+    match min_recipient_amount {
+        Some(min) if amount1 < min || amount2 < min => Err(ContractError::SlippageExceeded {}),
+        _ => Ok(()),
+    }
+}
+
+// Fails once the block time is past the deadline, so a deposit delayed in the mempool cannot
+// execute under a fee it was not sent for
+fn check_deadline(env: &Env, deadline: Option<Timestamp>) -> Result<(), ContractError> {
+    match deadline {
+        Some(deadline) if env.block.time > deadline => Err(ContractError::Expired {}),
+        _ => Ok(()),
+    }
+}
+
+// Bounds the recipient lists of deposits so that crediting them fits in the gas limit
+fn check_recipients(contract_info: &ContractInfo, count: usize) -> Result<(), ContractError> {
+    if count > contract_info.max_recipients as usize {
+        return Err(ContractError::TooManyRecipients {});
+    }
+
+    Ok(())
+}
+
+// Records the nonce of a deposit, failing if the depositor already used it so that a retried
+// deposit is not credited twice. Deposits without a nonce are not tracked.
+fn use_nonce(storage: &mut dyn Storage, depositor: &Addr, nonce: Option<&str>) -> Result<(), ContractError> {
+    if let Some(nonce) = nonce {
+        if NONCES.has(storage, (depositor.clone(), nonce)) {
+            return Err(ContractError::DuplicateNonce {});
+        }
+        NONCES.save(storage, (depositor.clone(), nonce), &true)?;
+    }
+
+    Ok(())
+}
+
+// Empty addresses are the most common client bug, they get their own error instead of the
+// generic one of addr_validate. A missing address is empty as well.
+fn check_not_empty<'a>(field: &str, addr: Option<&'a str>) -> Result<&'a str, ContractError> {
+    match addr {
+        Some(addr) if !addr.is_empty() => Ok(addr),
+        _ => Err(ContractError::EmptyAddress { field: field.to_string() }),
+    }
+}
+
+// Recipients must be valid addresses other than the contract itself, which could never withdraw
+fn validate_recipient(deps: Deps, env: &Env, contract_info: &ContractInfo, addr: &str) -> Result<Addr, ContractError> {
+    let addr = deps.api.addr_validate(addr)?;
+    if addr == env.contract.address {
+        return Err(ContractError::InvalidRecipient { addr: addr.to_string() });
+    }
+    if contract_info.forbid_owner_recipient && addr == contract_info.owner {
+        return Err(ContractError::OwnerRecipient {});
+    }
+
+    Ok(addr)
+}
+
+// Checks that the address is a cw20 contract by probing its token info
+// Returns the token info of the cw20 contract at addr
+fn validate_token(deps: Deps, addr: &str) -> Result<(Addr, TokenInfoResponse), ContractError> {
+    let token = deps.api.addr_validate(addr)?;
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(token.to_string(), &Cw20QueryMsg::TokenInfo {})
+        .map_err(|_| ContractError::InvalidToken { addr: token.to_string() })?;
+
+    Ok((token, token_info))
+}
+
+// The fee never exceeds the amount as the numerator is capped at the denominator, even when rounding up
+pub(crate) fn compute_fee(
+    amount: Uint128,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    rounding: RoundingMode,
+) -> StdResult<Uint128> {
+    let fee = amount.checked_mul(Uint128::from(fee_numerator))?;
+    let fee = match rounding {
+        RoundingMode::RoundDown => fee,
+        RoundingMode::RoundUp => fee.checked_add(Uint128::from(fee_denominator - 1))?,
+    };
+
+    Ok(fee.checked_div(Uint128::from(fee_denominator))?)
+}
+
+// fee_bps is the deprecated way of setting the fee, it cannot be combined with the ratio
+fn fee_ratio(
+    fee_numerator: Option<u64>,
+    fee_denominator: Option<u64>,
+    fee_bps: Option<u16>,
+) -> Result<(u64, u64), ContractError> {
+    match (fee_numerator, fee_denominator, fee_bps) {
+        (Some(fee_numerator), Some(fee_denominator), None) => Ok((fee_numerator, fee_denominator)),
+        (None, None, Some(fee_bps)) => Ok((fee_bps as u64, BPS_DENOMINATOR as u64)),
+        _ => Err(ContractError::InvalidFee {}),
+    }
+}
+
+// Fee of the deprecated bps fields of the responses, rounded down
+fn fee_bps_of(fee_numerator: u64, fee_denominator: u64) -> u16 {
+    (fee_numerator as u128 * BPS_DENOMINATOR as u128 / fee_denominator as u128) as u16
+}
+
+fn validate_fee(fee_numerator: u64, fee_denominator: u64) -> Result<(), ContractError> {
+    if fee_denominator == 0 || fee_numerator > fee_denominator {
+        return Err(ContractError::InvalidFee {});
+    }
+
+    Ok(())
+}
+
+// Splits a deposit into the fee, capped at max_fee when given, and the amounts of addr1 and addr2
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn compute_split(
+    amount: Uint128,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    split_bps: u16,
+    rounding: RoundingMode,
+    max_fee: Option<Uint128>,
+    remainder_to: RemainderTo,
+    dust_policy: DustPolicy,
+) -> StdResult<(Uint128, Uint128, Uint128)> {
+    let mut fee = compute_fee(amount, fee_numerator, fee_denominator, rounding)?;
+    if let Some(max_fee) = max_fee {
+        fee = fee.min(max_fee);
+    }
+
+    // both shares are rounded down and the remainder goes to remainder_to, e.g. 95 split evenly
+    // gives 47 to addr1 and 48 to addr2 with RemainderTo::Addr2, unless it joins the fee
+    let send_amount = amount.checked_sub(fee)?;
+    let amount1 = send_amount.multiply_ratio(split_bps, BPS_DENOMINATOR);
+    let amount2 = send_amount.multiply_ratio(BPS_DENOMINATOR - split_bps, BPS_DENOMINATOR);
+    let remainder = send_amount - amount1 - amount2;
+    let dust = pooled_dust(dust_policy, max_fee, fee, remainder);
+    match remainder_to {
+        RemainderTo::Addr1 => Ok((fee + dust, amount1 + remainder - dust, amount2)),
+        RemainderTo::Addr2 => Ok((fee + dust, amount1, amount2 + remainder - dust)),
+    }
+}
+
+// The part of the rounding remainder of a split that joins the fee. It is added once the fee is
+// capped and only as far as the fee stays within max_fee, the rest goes to the recipients.
+fn pooled_dust(dust_policy: DustPolicy, max_fee: Option<Uint128>, fee: Uint128, remainder: Uint128) -> Uint128 {
+    match (dust_policy, max_fee) {
+        (DustPolicy::LastRecipient, _) => Uint128::zero(),
+        (DustPolicy::FeePool, Some(max_fee)) => remainder.min(max_fee.saturating_sub(fee)),
+        (DustPolicy::FeePool, None) => remainder,
+    }
+}
+
+// Accrues the pooled part of the rounding remainder of a deposit and returns it
+fn pool_dust(
+    storage: &mut dyn Storage,
+    contract_info: &ContractInfo,
+    token: &Addr,
+    fee: Uint128,
+    remainder: Uint128,
+) -> StdResult<Uint128> {
+    let dust = pooled_dust(contract_info.dust_policy, max_fee_of(contract_info, token), fee, remainder);
+    accrue_fee(storage, contract_info, token, dust)?;
+
+    Ok(dust)
+}
+
+// the fee cap is expressed in the configured token
+fn max_fee_of(contract_info: &ContractInfo, token: &Addr) -> Option<Uint128> {
+    if *token == contract_info.token { contract_info.max_fee } else { None }
+}
+
+// compute_split with the configuration
+fn deposit_split(
+    contract_info: &ContractInfo,
+    token: &Addr,
+    amount: Uint128,
+) -> StdResult<(Uint128, Uint128, Uint128)> {
+    compute_split(
+        amount,
+        contract_info.fee_numerator,
+        contract_info.fee_denominator,
+        contract_info.split_bps,
+        contract_info.rounding,
+        max_fee_of(contract_info, token),
+        contract_info.remainder_to,
+        contract_info.dust_policy,
+    )
+}
+
+// The fee of a deposit that is not split in two, capped at max_fee
+fn deposit_fee(contract_info: &ContractInfo, token: &Addr, amount: Uint128) -> StdResult<Uint128> {
+    let fee = compute_fee(amount, contract_info.fee_numerator, contract_info.fee_denominator, contract_info.rounding)?;
+
+    Ok(match max_fee_of(contract_info, token) {
+        Some(max_fee) => fee.min(max_fee),
+        None => fee,
+    })
+}
+
+// Accrues the deposit fee and returns the amount left for the recipients
+fn take_fee(
+    storage: &mut dyn Storage,
+    contract_info: &ContractInfo,
+    token: &Addr,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let fee = deposit_fee(contract_info, token, amount)?;
+    accrue_fee(storage, contract_info, token, fee)?;
+
+    Ok(amount - fee)
+}
+
+fn accrue_fee(
+    storage: &mut dyn Storage,
+    contract_info: &ContractInfo,
+    token: &Addr,
+    fee: Uint128,
+) -> StdResult<()> {
+    if let FeeMode::Forward { .. } = contract_info.fee_mode {
+        // the deposit sends the fee to the recipient right away
+    } else if *token == contract_info.token {
+        add_fee_collected(storage, fee)?;
+    } else {
+        let total_fee = match TOKEN_FEE_COLLECTED.may_load(storage, token.clone())? {
+            Some(val) => val,
+            None => Uint128::zero()
+        };
+        TOKEN_FEE_COLLECTED.save(storage, token.clone(), &total_fee.checked_add(fee)?)?;
+    }
+
+    Ok(())
+}
+
+// FEE_COLLECTED is missing in contracts migrated from code without it, it is zero then
+fn load_fee_collected(storage: &dyn Storage) -> StdResult<Uint128> {
+    let fee_collected = match FEE_COLLECTED.may_load(storage)? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+
+    Ok(fee_collected)
+}
+
+fn add_fee_collected(storage: &mut dyn Storage, fee: Uint128) -> StdResult<()> {
+    let total_fee = load_fee_collected(storage)?.checked_add(fee)?;
+    FEE_COLLECTED.save(storage, &total_fee)
+}
+
+// Credits the amounts gathered over a deposit, one balance write per recipient
+fn flush_credits(
+    storage: &mut dyn Storage,
+    contract_info: &ContractInfo,
+    token: &Addr,
+    credits: BTreeMap<Addr, Uint128>,
+) -> StdResult<()> {
+    for (addr, amount) in credits {
+        credit(storage, contract_info, token, &addr, amount)?;
+    }
+
+    Ok(())
+}
+
+fn credit(
+    storage: &mut dyn Storage,
+    contract_info: &ContractInfo,
+    token: &Addr,
+    addr: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    if *token != contract_info.token {
+        let withdrawable = match TOKEN_WITHDRAWABLE.may_load(storage, (token.clone(), addr.clone()))? {
+            Some(val) => val,
+            None => Uint128::zero()
+        };
+
+        return TOKEN_WITHDRAWABLE.save(storage, (token.clone(), addr.clone()), &withdrawable.checked_add(amount)?);
+    }
+
+    let withdrawable = match WITHDRAWABLE.may_load(storage, addr.clone())? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+
+    WITHDRAWABLE.save(storage, addr.clone(), &withdrawable.checked_add(amount)?)?;
+
+    earn(storage, addr, amount)
+}
+
+// Fails if amount more of the configured token owed would push the total over
+// max_total_withdrawable. Every path crediting new tokens goes through it, restoring a failed
+// withdrawal does not as the amount was owed before.
+fn check_cap(deps: Deps, contract_info: &ContractInfo, amount: Uint128) -> Result<(), ContractError> {
+    if let Some(cap) = contract_info.max_total_withdrawable {
+        if total_withdrawable(deps)?.checked_add(amount).map_err(StdError::from)? > cap {
+            return Err(ContractError::CapExceeded {});
+        }
+    }
+
+    Ok(())
+}
+
+// Books a new credit of the configured token, whether withdrawable right away or not
+fn earn(storage: &mut dyn Storage, addr: &Addr, amount: Uint128) -> StdResult<()> {
+    let total_earned = match TOTAL_EARNED.may_load(storage, addr.clone())? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+    TOTAL_EARNED.save(storage, addr.clone(), &total_earned.checked_add(amount)?)?;
+
+    increase_total_withdrawable(storage, amount)
+}
+
+// Counters of the stats read as zero when missing, e.g. on a contract migrated from before them
+fn add_stat(storage: &mut dyn Storage, counter: &Item<Uint128>, amount: Uint128) -> StdResult<()> {
+    let total = counter.may_load(storage)?.unwrap_or_default();
+    counter.save(storage, &total.checked_add(amount)?)
+}
+
+fn sub_stat(storage: &mut dyn Storage, counter: &Item<Uint128>, amount: Uint128) -> StdResult<()> {
+    let total = counter.may_load(storage)?.unwrap_or_default();
+    counter.save(storage, &total.saturating_sub(amount))
+}
+
+fn increase_total_withdrawable(storage: &mut dyn Storage, amount: Uint128) -> StdResult<()> {
+    let total = TOTAL_WITHDRAWABLE.may_load(storage)?.unwrap_or_default();
+    TOTAL_WITHDRAWABLE.save(storage, &total.checked_add(amount)?)
+}
+
+fn decrease_total_withdrawable(storage: &mut dyn Storage, amount: Uint128) -> StdResult<()> {
+    let total = TOTAL_WITHDRAWABLE.may_load(storage)?.unwrap_or_default();
+    TOTAL_WITHDRAWABLE.save(storage, &total.checked_sub(amount)?)
+}
+
+// What the contract owes in the configured token, read from every balance instead of the running total
+fn sum_withdrawable(storage: &dyn Storage) -> StdResult<Uint128> {
+    let mut total = Uint128::zero();
+    for item in WITHDRAWABLE.range(storage, None, None, Order::Ascending) {
+        total = total.checked_add(item?.1)?;
+    }
+    for item in LOCKED.range(storage, None, None, Order::Ascending) {
+        total = total.checked_add(item?.1)?;
+    }
+    for item in VESTING.range(storage, None, None, Order::Ascending) {
+        for entry in item?.1 {
+            total = total.checked_add(entry.total - entry.released)?;
+        }
+    }
+
+    Ok(total)
+}
+
+// Credits an amount of the configured token that can only be withdrawn from unlock_time on
+fn lock(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    unlock_time: Timestamp,
+    amount: Uint128,
+) -> StdResult<()> {
+    let key = (addr.clone(), unlock_time.seconds());
+    let locked = LOCKED.may_load(storage, key.clone())?.unwrap_or_default();
+    LOCKED.save(storage, key, &locked.checked_add(amount)?)?;
+
+    earn(storage, addr, amount)
+}
+
+// Credits an amount of the configured token that is released linearly over the schedule
+fn vest(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    schedule: &VestingSchedule,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut entries = VESTING.may_load(storage, addr.clone())?.unwrap_or_default();
+    entries.push(VestingEntry {
+        start: schedule.start,
+        end: schedule.end,
+        total: amount,
+        released: Uint128::zero(),
+    });
+    VESTING.save(storage, addr.clone(), &entries)?;
+
+    earn(storage, addr, amount)
+}
+
+// Amount of a vesting entry that has vested but is not released yet
+fn claimable(entry: &VestingEntry, env: &Env) -> Uint128 {
+    let now = env.block.time.seconds();
+    let vested = if now <= entry.start.seconds() {
+        Uint128::zero()
+    } else if now >= entry.end.seconds() {
+        entry.total
+    } else {
+        entry.total.multiply_ratio(
+            now - entry.start.seconds(),
+            entry.end.seconds() - entry.start.seconds(),
+        )
+    };
+
+    vested - entry.released
+}
+
+// Locked entries of addr whose unlock time has passed
+fn unlocked(storage: &dyn Storage, env: &Env, addr: &Addr) -> StdResult<Vec<(u64, Uint128)>> {
+    LOCKED
+        .prefix(addr.clone())
+        .range(storage, None, Some(Bound::inclusive(env.block.time.seconds())), Order::Ascending)
+        .collect()
+}
+
+// Moves the unlocked and vested amounts of addr into its withdrawable balance
+fn release_unlocked(storage: &mut dyn Storage, env: &Env, addr: &Addr) -> StdResult<()> {
+    let mut released = Uint128::zero();
+    for (unlock_time, amount) in unlocked(storage, env, addr)? {
+        LOCKED.remove(storage, (addr.clone(), unlock_time));
+        released = released.checked_add(amount)?;
+    }
+
+    if let Some(mut entries) = VESTING.may_load(storage, addr.clone())? {
+        for entry in entries.iter_mut() {
+            let amount = claimable(entry, env);
+            entry.released += amount;
+            released = released.checked_add(amount)?;
+        }
+        // fully released entries are dropped
+        entries.retain(|entry| entry.released < entry.total);
+        if entries.is_empty() {
+            VESTING.remove(storage, addr.clone());
+        } else {
+            VESTING.save(storage, addr.clone(), &entries)?;
+        }
+    }
+
+    if released.is_zero() {
+        return Ok(());
+    }
+
+    let withdrawable = WITHDRAWABLE.may_load(storage, addr.clone())?.unwrap_or_default();
+    WITHDRAWABLE.save(storage, addr.clone(), &withdrawable.checked_add(released)?)
+}
+
+fn deposit_native(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: DepositMsg,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // Validations
+    let denom = match &contract_info.native_denom {
+        Some(denom) => denom.as_str(),
+        None => {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Native deposits disabled".to_string(),
+            }))
+        }
+    };
+    if info.funds.len() != 1 || info.funds[0].denom != denom {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Invalid funds".to_string(),
+        }));
+    }
+    if !is_allowed_depositor(deps.storage, &info.sender)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    check_deadline(&env, msg.deadline)?;
+    use_nonce(deps.storage, &info.sender, msg.nonce.as_deref())?;
+    if msg.unlock_time.is_some() || msg.vesting.is_some() {
+        return Err(ContractError::Std(StdError::GenericErr {
+            msg: "Release schedule not supported".to_string(),
+        }));
+    }
+    let amount = info.funds[0].amount;
+    let addr1 = check_not_empty("addr1", msg.addr1.as_deref())?;
+    let addr2 = check_not_empty("addr2", msg.addr2.as_deref())?;
+    let addr1 = validate_recipient(deps.as_ref(), &env, &contract_info, addr1)?;
+    let addr2 = validate_recipient(deps.as_ref(), &env, &contract_info, addr2)?;
+
+    let (fee, amount1, amount2) = compute_split(
+        amount,
+        contract_info.fee_numerator,
+        contract_info.fee_denominator,
+        contract_info.split_bps,
+        contract_info.rounding,
+        None,
+        contract_info.remainder_to,
+        contract_info.dust_policy,
+    )?;
+    check_slippage(msg.min_recipient_amount, amount1, amount2)?;
+
+    // forwarded fees are sent on with the deposit like those of the tokens
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    match &contract_info.fee_mode {
+        FeeMode::Forward { recipient } => {
+            if !fee.is_zero() {
+                msgs.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: recipient.clone(),
+                    amount: vec![Coin { denom: denom.to_string(), amount: fee }],
+                }));
+            }
+        }
+        FeeMode::Accrue => accrue_native_fee(deps.storage, denom, fee)?,
+    }
+
+    credit_native(deps.storage, denom, &addr1, amount1)?;
+    credit_native(deps.storage, denom, &addr2, amount2)?;
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "deposit_native")
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+        .add_attribute("fee", fee)
+        .add_attribute("addr1", addr1)
+        .add_attribute("amount1", amount1)
+        .add_attribute("addr2", addr2)
+        .add_attribute("amount2", amount2))
+}
+
+fn accrue_native_fee(storage: &mut dyn Storage, denom: &str, fee: Uint128) -> StdResult<()> {
+    let total_fee = match NATIVE_FEE_COLLECTED.may_load(storage, denom)? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+    NATIVE_FEE_COLLECTED.save(storage, denom, &total_fee.checked_add(fee)?)
+}
+
+fn credit_native(
+    storage: &mut dyn Storage,
+    denom: &str,
+    addr: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let withdrawable = match NATIVE_WITHDRAWABLE.may_load(storage, (denom, addr.clone()))? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+
+    NATIVE_WITHDRAWABLE.save(storage, (denom, addr.clone()), &withdrawable.checked_add(amount)?)
+}
+
+fn withdraw_native(
+    deps: DepsMut,
+    info: MessageInfo,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let denom = match contract_info.native_denom {
+        Some(denom) => denom,
+        None => {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Native deposits disabled".to_string(),
+            }))
+        }
+    };
+
+    check_not_frozen(deps.storage, &info.sender)?;
+    let remaining = debit(deps.storage, &NATIVE_WITHDRAWABLE, (&denom, info.sender.clone()), amount)?;
+
+    let recipient = deps.api.addr_validate(recipient.as_deref().unwrap_or(info.sender.as_str()))?;
+    let msgs: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: vec![Coin { denom, amount }],
+    })];
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "withdraw_native")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount)
+        .add_attribute("remaining", remaining))
+}
+
+fn withdraw_native_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    // validate owner
+    if contract_info.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let denom = match contract_info.native_denom {
+        Some(denom) => denom,
+        None => {
+            return Err(ContractError::Std(StdError::GenericErr {
+                msg: "Native deposits disabled".to_string(),
+            }))
+        }
+    };
+
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => contract_info.fee_recipient.clone(),
+    };
+
+    let fee = match NATIVE_FEE_COLLECTED.may_load(deps.storage, &denom)? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+    if fee.is_zero() {
+        return Err(ContractError::ZeroAmount {});
+    }
+    NATIVE_FEE_COLLECTED.save(deps.storage, &denom, &Uint128::zero())?;
+
+    let msgs: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: vec![Coin { denom: denom.clone(), amount: fee }],
+    })];
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "withdraw_native_fee")
+        .add_attribute("denom", denom)
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", fee))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    // a failed burn aborts the whole withdrawal, the fee stays collected
+    if msg.id == BURN_FEE_REPLY_ID {
+        return match msg.result {
+            SubMsgResult::Ok(_) => Ok(Response::new()),
+            SubMsgResult::Err(error) => Err(ContractError::BurnFailed { error }),
+        };
+    }
+
+    let pending = PENDING_WITHDRAWALS.load(deps.storage, msg.id)?;
+    PENDING_WITHDRAWALS.remove(deps.storage, msg.id);
+
+    match msg.result {
+        SubMsgResult::Ok(_) => Ok(Response::new()),
+        SubMsgResult::Err(err) => {
+            // restore the debited balance, this is not a new earning
+            let contract_info = CONTRACT_INFO.load(deps.storage)?;
+            if pending.token == contract_info.token {
+                // the withdrawal fee is given back as well
+                let amount = pending.amount.checked_add(pending.fee).map_err(StdError::from)?;
+                WITHDRAWABLE.update(deps.storage, pending.account.clone(), |withdrawable| -> StdResult<_> {
+                    Ok(withdrawable.unwrap_or_default().checked_add(amount)?)
+                })?;
+                increase_total_withdrawable(deps.storage, amount)?;
+                if !pending.fee.is_zero() {
+                    let fee_collected = load_fee_collected(deps.storage)?.checked_sub(pending.fee).map_err(StdError::from)?;
+                    FEE_COLLECTED.save(deps.storage, &fee_collected)?;
+                }
+                sub_stat(deps.storage, &TOTAL_WITHDRAWN, pending.amount)?;
+                sub_stat(deps.storage, &TOTAL_FEES, pending.fee)?;
+                // nor does the failed withdrawal start a cooldown
+                match pending.last_withdrawal {
+                    Some(time) => LAST_WITHDRAWAL.save(deps.storage, pending.account.clone(), &time)?,
+                    None => LAST_WITHDRAWAL.remove(deps.storage, pending.account.clone()),
+                }
+            } else {
+                TOKEN_WITHDRAWABLE.update(
+                    deps.storage,
+                    (pending.token.clone(), pending.account.clone()),
+                    |withdrawable| -> StdResult<_> {
+                        Ok(withdrawable.unwrap_or_default().checked_add(pending.amount)?)
+                    },
+                )?;
+            }
+
+            Ok(Response::new()
+                .add_attribute("action", "withdraw_failed")
+                .add_attribute("token", pending.token)
+                .add_attribute("account", pending.account)
+                .add_attribute("amount", pending.amount)
+                .add_attribute("error", err))
+        }
+    }
+}
+
+// Only callable by the chain, e.g. through a governance proposal
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::SetFee { fee_numerator, fee_denominator, fee_bps } => {
+            let (fee_numerator, fee_denominator) = fee_ratio(fee_numerator, fee_denominator, fee_bps)?;
+            set_fee(deps.storage, fee_numerator, fee_denominator)?;
+
+            Ok(Response::new()
+                .add_attribute("method", "sudo_set_fee")
+                .add_attribute("fee_numerator", fee_numerator.to_string())
+                .add_attribute("fee_denominator", fee_denominator.to_string()))
+        }
+    }
+}
+
+// Only future deposits use the new fee, credited balances are unaffected
+fn set_fee(storage: &mut dyn Storage, fee_numerator: u64, fee_denominator: u64) -> Result<(), ContractError> {
+    validate_fee(fee_numerator, fee_denominator)?;
+
+    CONTRACT_INFO.update(storage, |mut contract_info| -> StdResult<_> {
+        contract_info.fee_numerator = fee_numerator;
+        contract_info.fee_denominator = fee_denominator;
+        Ok(contract_info)
+    })?;
+
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    // the first release saved no cw2 version
+    match CONTRACT.may_load(deps.storage)? {
+        Some(stored) => {
+            if stored.contract != CONTRACT_NAME {
+                return Err(ContractError::Std(StdError::GenericErr {
+                    msg: "Cannot migrate from a different contract".to_string(),
+                }));
+            }
+
+            // reject downgrades
+            let stored_version: Version = stored.version.parse()?;
+            let version: Version = CONTRACT_VERSION.parse()?;
+            if stored_version > version {
+                return Err(ContractError::Std(StdError::GenericErr {
+                    msg: "Cannot migrate from a newer version".to_string(),
+                }));
+            }
+        }
+        None => migrate_legacy_config(deps.branch())?,
+    }
+
+    // the running total is missing on contracts from before it, recompute it from the balances
+    let total = sum_withdrawable(deps.storage)?;
+    TOTAL_WITHDRAWABLE.save(deps.storage, &total)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attribute("method", "migrate"))
+}
+
+// The config of the first release only held the token and the owner, the settings added since
+// take their default, which match the behavior of that release
+fn migrate_legacy_config(deps: DepsMut) -> Result<(), ContractError> {
+    let legacy = LEGACY_CONTRACT_INFO.load(deps.storage)?;
+    let contract_info = contract_info_from(
+        deps.as_ref(),
+        InstantiateMsg {
+            token: legacy.token.to_string(),
+            owner: legacy.owner.to_string(),
+            fee_numerator: None,
+            fee_denominator: None,
+            fee_bps: None,
+            split_bps: BPS_DENOMINATOR / 2,
+            native_denom: None,
+            min_deposit: None,
+            rounding: None,
+            fee_mode: None,
+            withdraw_cooldown: None,
+            max_fee: None,
+            burn_fee: None,
+            max_total_withdrawable: None,
+            shareholders: None,
+            remainder_to: None,
+            recovery_addr: None,
+            withdraw_fee_bps: None,
+            forbid_owner_recipient: None,
+            deflationary_token: None,
+            check_contract_balance: None,
+            max_recipients: None,
+            group: None,
+            dust_policy: None,
+        },
+    )?;
+    CONTRACT_INFO.save(deps.storage, &contract_info)?;
+
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Owner {} => to_binary(&get_owner(deps)?),
+        QueryMsg::PendingOwner {} => to_binary(&pending_owner(deps)?),
+        QueryMsg::IsOwner { addr } => to_binary(&is_owner(deps, addr)?),
+        QueryMsg::Withdrawable { addr } => to_binary(&withdrawable(deps, env, addr)?),
+        QueryMsg::FeeCollected {} => to_binary(&fee_collected(deps)?),
+        QueryMsg::Config {} => to_binary(&get_config(deps)?),
+        QueryMsg::TotalEarned { addr } => to_binary(&total_earned(deps, addr)?),
+        QueryMsg::NativeWithdrawable { addr } => to_binary(&native_withdrawable(deps, addr)?),
+        QueryMsg::TokenWithdrawable { token, addr } => to_binary(&token_withdrawable(deps, token, addr)?),
+        QueryMsg::AllWithdrawable { start_after, limit } => to_binary(&all_withdrawable(deps, start_after, limit)?),
+        QueryMsg::TokenBalance {} => to_binary(&token_balance(deps, env)?),
+        QueryMsg::TotalWithdrawable {} => to_binary(&total_withdrawable(deps)?),
+        QueryMsg::PreviewDeposit { amount } => to_binary(&preview_deposit(deps, amount)?),
+        QueryMsg::Deposit { id } => to_binary(&DEPOSITS.load(deps.storage, id)?),
+        QueryMsg::DepositsByRecipient { addr, start_after, limit } => {
+            to_binary(&deposits_by_recipient(deps, addr, start_after, limit)?)
+        }
+        QueryMsg::FeeInfo {} => to_binary(&fee_info(deps)?),
+        QueryMsg::Stats {} => to_binary(&stats(deps)?),
+        QueryMsg::WithdrawableBatch { addrs } => to_binary(&withdrawable_batch(deps, env, addrs)?),
+        QueryMsg::PreviewWithdraw { addr, amount } => to_binary(&preview_withdraw(deps, env, addr, amount)?),
+        QueryMsg::ShareOf { addr, amount } => to_binary(&share_of(deps, addr, amount)?),
+        QueryMsg::Depositors { start_after, limit } => to_binary(&list_addrs(deps, &DEPOSITORS, start_after, limit)?),
+        QueryMsg::Operators { start_after, limit } => to_binary(&list_addrs(deps, &OPERATORS, start_after, limit)?),
+    }
+}
+
+fn get_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    Ok(ConfigResponse {
+        token: contract_info.token.to_string(),
+        owner: contract_info.owner.to_string(),
+        fee_numerator: contract_info.fee_numerator,
+        fee_denominator: contract_info.fee_denominator,
+        fee_bps: fee_bps_of(contract_info.fee_numerator, contract_info.fee_denominator),
+        split_bps: contract_info.split_bps,
+        native_denom: contract_info.native_denom,
+        min_deposit: contract_info.min_deposit,
+        rounding: contract_info.rounding,
+        fee_mode: contract_info.fee_mode,
+        withdraw_cooldown: contract_info.withdraw_cooldown,
+        max_fee: contract_info.max_fee,
+        burn_fee: contract_info.burn_fee,
+        max_total_withdrawable: contract_info.max_total_withdrawable,
+        remainder_to: contract_info.remainder_to,
+        recovery_addr: contract_info.recovery_addr.map(|addr| addr.to_string()),
+        decimals: contract_info.decimals,
+        symbol: contract_info.symbol,
+        withdraw_fee_bps: contract_info.withdraw_fee_bps,
+        forbid_owner_recipient: contract_info.forbid_owner_recipient,
+        deflationary_token: contract_info.deflationary_token,
+        check_contract_balance: contract_info.check_contract_balance,
+        fee_recipient: contract_info.fee_recipient.to_string(),
+        max_recipients: contract_info.max_recipients,
+        group: contract_info.group.map(|addr| addr.to_string()),
+        dust_policy: contract_info.dust_policy,
+    })
+}
+
+fn fee_info(deps: Deps) -> StdResult<FeeInfoResponse> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    Ok(FeeInfoResponse {
+        fee_numerator: contract_info.fee_numerator,
+        fee_denominator: contract_info.fee_denominator,
+        fee_bps: fee_bps_of(contract_info.fee_numerator, contract_info.fee_denominator),
+        max_fee: contract_info.max_fee,
+        rounding: contract_info.rounding,
+    })
+}
+
+fn get_owner(deps: Deps) -> StdResult<OwnerResponse> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    Ok(OwnerResponse { owner: contract_info.owner.to_string() })
+}
+
+fn stats(deps: Deps) -> StdResult<StatsResponse> {
+    Ok(StatsResponse {
+        total_deposited: TOTAL_DEPOSITED.may_load(deps.storage)?.unwrap_or_default(),
+        total_withdrawn: TOTAL_WITHDRAWN.may_load(deps.storage)?.unwrap_or_default(),
+        total_fees_collected: TOTAL_FEES.may_load(deps.storage)?.unwrap_or_default(),
+        deposit_count: DEPOSIT_COUNT.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+// compares validated addresses, so clients do not have to normalize them
+fn is_owner(deps: Deps, addr: String) -> StdResult<bool> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    Ok(contract_info.owner == addr)
+}
+
+fn pending_owner(deps: Deps) -> StdResult<Option<String>> {
+    Ok(PENDING_OWNER.may_load(deps.storage)?.map(|addr| addr.to_string()))
+}
+
+// includes the locked and vesting amounts that can already be released
+fn withdrawable(deps: Deps, env: Env, addr: String) -> StdResult<WithdrawableResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let mut withdrawable = match WITHDRAWABLE.may_load(deps.storage, addr.clone())? {
+        Some(val) => val,
+        None => Uint128::zero()
+    };
+
+    for (_, amount) in unlocked(deps.storage, &env, &addr)? {
+        withdrawable = withdrawable.checked_add(amount)?;
+    }
+    for entry in VESTING.may_load(deps.storage, addr)?.unwrap_or_default() {
+        withdrawable = withdrawable.checked_add(claimable(&entry, &env))?;
+    }
+
+    Ok(WithdrawableResponse { amount: withdrawable })
+}
+
+// balance of the configured token actually held by the contract
+fn token_balance(deps: Deps, env: Env) -> StdResult<Uint128> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    let balance: Cw20BalanceResponse = deps.querier.query_wasm_smart(
+        contract_info.token.to_string(),
+        &Cw20QueryMsg::Balance {
+            address: env.contract.address.to_string(),
+        },
+    )?;
+
+    Ok(balance.balance)
+}
+
+// same amounts as a Deposit of the configured token, without touching the state
+fn preview_deposit(deps: Deps, amount: Uint128) -> StdResult<PreviewResponse> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+
+    let (fee, amount1, amount2) = deposit_split(&contract_info, &contract_info.token, amount)?;
+
+    Ok(PreviewResponse { fee, amount1, amount2 })
+}
+
+// What addr would be credited if amount of the configured token was deposited without
+// recipients, split by the members of the group when one is configured, by the shares otherwise
+fn share_of(deps: Deps, addr: String, amount: Uint128) -> StdResult<Uint128> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let addr = deps.api.addr_validate(&addr)?;
+
+    let recipients = match &contract_info.group {
+        Some(group) => group_members(deps, &contract_info, group)
+            .map_err(|err| StdError::generic_err(err.to_string()))?
+            .into_iter()
+            .map(|(member, weight)| Ok((deps.api.addr_validate(&member)?, weight)))
+            .collect::<StdResult<Vec<(Addr, u64)>>>()?,
+        None => SHARES
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<(Addr, u64)>>>()?,
+    };
+    let total_weight: u128 = recipients.iter().map(|(_, weight)| *weight as u128).sum();
+    if total_weight == 0 {
+        return Ok(Uint128::zero());
+    }
+
+    let fee = deposit_fee(&contract_info, &contract_info.token, amount)?;
+    let (amounts, _) = weighted_amounts(&contract_info, &contract_info.token, fee, amount - fee, &recipients, total_weight);
+
+    Ok(recipients
+        .iter()
+        .zip(amounts)
+        .find(|((recipient, _), _)| *recipient == addr)
+        .map(|(_, share)| share)
+        .unwrap_or_default())
+}
+
+fn withdrawable_batch(deps: Deps, env: Env, addrs: Vec<String>) -> StdResult<Vec<(String, Uint128)>> {
+    if addrs.len() > MAX_BATCH {
+        return Err(StdError::generic_err(format!("Too many addresses, at most {} are allowed", MAX_BATCH)));
+    }
+
+    addrs
+        .into_iter()
+        .map(|addr| {
+            let amount = withdrawable(deps, env.clone(), addr.clone())?.amount;
+            Ok((addr, amount))
+        })
+        .collect()
+}
+
+// What a withdrawal of amount by addr would transfer, failing like the withdrawal itself: through
+// the same frozen, cooldown and contract balance checks
+fn preview_withdraw(deps: Deps, env: Env, addr: String, amount: Uint128) -> StdResult<PreviewWithdrawResponse> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let account = deps.api.addr_validate(&addr)?;
+
+    check_not_frozen(deps.storage, &account)
+        .and_then(|_| check_cooldown_elapsed(deps.storage, &contract_info, &env, &account))
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let available = withdrawable(deps, env.clone(), addr)?.amount;
+    if amount.is_zero() {
+        return Err(StdError::generic_err(ContractError::ZeroAmount {}.to_string()));
+    }
+    if amount > available {
+        return Err(StdError::generic_err(
+            ContractError::InsufficientFunds { requested: amount, available }.to_string(),
+        ));
+    }
+    check_contract_balance(deps, &env, &contract_info, amount - withdraw_fee_of(&contract_info, amount))
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    Ok(PreviewWithdrawResponse {
+        amount: amount - withdraw_fee_of(&contract_info, amount),
+        remaining: available - amount,
+    })
+}
+
+fn total_withdrawable(deps: Deps) -> StdResult<Uint128> {
+    Ok(TOTAL_WITHDRAWABLE.may_load(deps.storage)?.unwrap_or_default())
+}
+
+fn fee_collected(deps: Deps) -> StdResult<Uint128> {
+    load_fee_collected(deps.storage)
+}
+
+fn total_earned(deps: Deps, addr: String) -> StdResult<Uint128> {
+    match TOTAL_EARNED.may_load(deps.storage, deps.api.addr_validate(&addr)?)? {
+        Some(val) => Ok(val),
+        None => Ok(Uint128::zero())
+    }
+}
+
+fn native_withdrawable(deps: Deps, addr: String) -> StdResult<Uint128> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let denom = match contract_info.native_denom {
+        Some(denom) => denom,
+        None => return Ok(Uint128::zero()),
+    };
+
+    match NATIVE_WITHDRAWABLE.may_load(deps.storage, (&denom, deps.api.addr_validate(&addr)?))? {
+        Some(val) => Ok(val),
+        None => Ok(Uint128::zero())
+    }
+}
+
+fn token_withdrawable(deps: Deps, token: String, addr: String) -> StdResult<Uint128> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let token = deps.api.addr_validate(&token)?;
+    let addr = deps.api.addr_validate(&addr)?;
+
+    // the configured token keeps its own balances
+    let withdrawable = if token == contract_info.token {
+        WITHDRAWABLE.may_load(deps.storage, addr)?
+    } else {
+        TOKEN_WITHDRAWABLE.may_load(deps.storage, (token, addr))?
+    };
+
+    match withdrawable {
+        Some(val) => Ok(val),
+        None => Ok(Uint128::zero())
+    }
+}
+
+// Page size of the paginated queries, bounded so that none iterates without limit
+pub(crate) fn clamp_limit(limit: Option<u32>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize
+}
+
+fn all_withdrawable(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, Uint128)>> {
+    let limit = clamp_limit(limit);
+    let start = match start_after {
+        Some(addr) => Some(Bound::exclusive(deps.api.addr_validate(&addr)?)),
+        None => None,
+    };
+
+    WITHDRAWABLE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(addr, amount)| (addr.to_string(), amount)))
+        .collect()
+}
+
+// Ids of the deposits that credited the address and the amount each credited, oldest first
+fn deposits_by_recipient(
+    deps: Deps,
+    addr: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, Uint128)>> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let limit = clamp_limit(limit);
+    let start = start_after.map(Bound::exclusive);
+
+    RECIPIENT_DEPOSITS
+        .prefix(addr)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+// Lists the addresses of an allowlist, revoked ones are removed from the map
+fn list_addrs(
+    deps: Deps,
+    addrs: &Map<Addr, bool>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<String>> {
+    let limit = clamp_limit(limit);
+    let start = match start_after {
+        Some(addr) => Some(Bound::exclusive(deps.api.addr_validate(&addr)?)),
+        None => None,
+    };
+
+    addrs
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|addr| addr.to_string()))
+        .collect()
 }
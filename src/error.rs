@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,4 +8,106 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Invalid Receive hook message")]
+    InvalidHookMsg {},
+
+    #[error("Owner cannot be the token contract")]
+    OwnerIsToken {},
+
+    #[error("Invalid token {addr}: not a cw20 contract")]
+    InvalidToken { addr: String },
+
+    #[error("Outstanding balances must be withdrawn first")]
+    OutstandingBalances {},
+
+    #[error("Invalid fee")]
+    InvalidFee {},
+
+    #[error("Invalid zero amount")]
+    ZeroAmount {},
+
+    #[error("Insufficient funds: requested {requested}, available {available}")]
+    InsufficientFunds { requested: Uint128, available: Uint128 },
+
+    #[error("Account frozen")]
+    AccountFrozen {},
+
+    #[error("Withdrawal cooldown still active")]
+    CooldownActive {},
+
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw {},
+
+    #[error("Deposit too small")]
+    DepositTooSmall {},
+
+    #[error("Empty address in {field}")]
+    EmptyAddress { field: String },
+
+    #[error("Empty recipients")]
+    EmptyRecipients {},
+
+    #[error("Invalid zero total weight")]
+    ZeroTotalWeight {},
+
+    #[error("Invalid percent, at most 10000 bps")]
+    InvalidPercent {},
+
+    #[error("Invalid recipient {addr}: the contract cannot be credited")]
+    InvalidRecipient { addr: String },
+
+    #[error("Batch amounts sum to {actual} but {expected} was received")]
+    BatchAmountMismatch { expected: Uint128, actual: Uint128 },
+
+    #[error("Recipient amounts sum to {actual} but {expected} is left after the fee")]
+    ExactAmountMismatch { expected: Uint128, actual: Uint128 },
+
+    #[error("Deposits of exact amounts are not supported for a deflationary token")]
+    DeflationaryExactAmounts {},
+
+    #[error("No recipients given and neither a group nor shares configured")]
+    NoRecipients {},
+
+    #[error("Duplicate shareholder {addr}")]
+    DuplicateShareholder { addr: String },
+
+    #[error("Too many recipients")]
+    TooManyRecipients {},
+
+    #[error("The owner cannot be a recipient")]
+    OwnerRecipient {},
+
+    #[error("A recipient would be credited less than the minimum amount")]
+    SlippageExceeded {},
+
+    #[error("Token {addr} is not accepted")]
+    TokenNotAccepted { addr: String },
+
+    #[error("Deposit exceeds the cap of the total withdrawable")]
+    CapExceeded {},
+
+    #[error("Fees are forwarded on deposit, there are none to withdraw")]
+    FeeModeIsForward {},
+
+    #[error("Fee burn failed, the token must implement cw20 Burn: {error}")]
+    BurnFailed { error: String },
+
+    #[error("Insufficient contract balance to pay the withdrawal")]
+    InsufficientContractBalance {},
+
+    #[error("Deposit nonce already used")]
+    DuplicateNonce {},
+
+    #[error("Deposit deadline expired")]
+    Expired {},
+
+    #[error("Semver parsing error: {0}")]
+    SemVer(String),
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
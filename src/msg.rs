@@ -1,4 +1,4 @@
-use cosmwasm_std::{Uint128};
+use cosmwasm_std::{Binary, Timestamp, Uint128};
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -7,15 +7,128 @@ use serde::{Deserialize, Serialize};
 pub struct InstantiateMsg {
     pub token: String,
     pub owner: String,
+    // fee = amount * fee_numerator / fee_denominator, set together or not at all for 50 / 1000
+    pub fee_numerator: Option<u64>,
+    pub fee_denominator: Option<u64>,
+    // deprecated, same as a fee_numerator of fee_bps over a fee_denominator of 10000
+    #[serde(default)]
+    pub fee_bps: Option<u16>,
+    pub split_bps: u16,
+    pub native_denom: Option<String>,
+    pub min_deposit: Option<Uint128>,
+    pub rounding: Option<RoundingMode>,
+    pub fee_mode: Option<FeeMode>,
+    pub withdraw_cooldown: Option<u64>,
+    pub max_fee: Option<Uint128>,
+    pub burn_fee: Option<bool>,
+    pub max_total_withdrawable: Option<Uint128>,
+    // recipients of the deposits that name none when there is no group, split by weight
+    pub shareholders: Option<Vec<(String, u64)>>,
+    pub remainder_to: Option<RemainderTo>,
+    pub recovery_addr: Option<String>,
+    pub withdraw_fee_bps: Option<u16>,
+    pub forbid_owner_recipient: Option<bool>,
+    pub deflationary_token: Option<bool>,
+    pub check_contract_balance: Option<bool>,
+    // 20 by default
+    pub max_recipients: Option<u32>,
+    pub group: Option<String>,
+    pub dust_policy: Option<DustPolicy>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    Withdraw { amount: Uint128 },
+    Withdraw {
+        amount: Uint128,
+        recipient: Option<String>,
+        // send the tokens to this contract with the msg hook instead of transferring them
+        #[serde(default)]
+        forward_contract: Option<String>,
+        #[serde(default)]
+        forward_msg: Option<Binary>,
+    },
     WithdrawAll {},
-    WithdrawFee {},
+    // withdraws bps / 10000 of the withdrawable balance
+    WithdrawPercent { bps: u16 },
+    // same as a forwarded Withdraw, the contract receives the tokens along with the msg hook
+    WithdrawAndExecute {
+        amount: Uint128,
+        contract: String,
+        msg: Binary,
+    },
+    WithdrawFee {
+        recipient: Option<String>,
+        amount: Option<Uint128>,
+    },
     Receive(Cw20ReceiveMsg),
+    TransferOwnership { new_owner: String },
+    AcceptOwnership {},
+    DepositNative(DepositMsg),
+    WithdrawNative {
+        amount: Uint128,
+        recipient: Option<String>,
+    },
+    WithdrawNativeFee { recipient: Option<String> },
+    WithdrawToken {
+        token: String,
+        amount: Uint128,
+        recipient: Option<String>,
+    },
+    WithdrawTokenFee {
+        token: String,
+        recipient: Option<String>,
+    },
+    SweepUnallocated {},
+    UpdateToken { token: String },
+    AddDepositor { addr: String },
+    RemoveDepositor { addr: String },
+    AddOperator { addr: String },
+    RemoveOperator { addr: String },
+    // blocks the withdrawals of a compromised account, deposits still credit it
+    FreezeAccount { addr: String },
+    UnfreezeAccount { addr: String },
+    WithdrawFor {
+        beneficiary: String,
+        amount: Uint128,
+    },
+    // fee_bps is the deprecated form of fee_numerator over a fee_denominator of 10000
+    UpdateFee {
+        #[serde(default)]
+        fee_numerator: Option<u64>,
+        #[serde(default)]
+        fee_denominator: Option<u64>,
+        #[serde(default)]
+        fee_bps: Option<u16>,
+    },
+    UpdateMinDeposit { min_deposit: Uint128 },
+    UpdateFeeRecipient { recipient: String },
+    // splits the unallocated balance of the configured token evenly, without fee
+    DistributeBalance { recipients: Vec<String> },
+    CreditBalance {
+        recipient: String,
+        amount: Uint128,
+    },
+    UpdateShares {
+        shareholders: Vec<(String, u64)>,
+    },
+    Revoke {
+        recipient: String,
+        amount: Uint128,
+    },
+    SetTokenConfig {
+        token: String,
+        #[serde(default)]
+        fee_numerator: Option<u64>,
+        #[serde(default)]
+        fee_denominator: Option<u64>,
+        #[serde(default)]
+        fee_bps: Option<u16>,
+        min_deposit: Option<Uint128>,
+        enabled: bool,
+    },
+    // zeroes the collected fees without transferring them, e.g. after sending them out of band
+    ResetFeeAccounting {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -23,13 +136,236 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     Withdrawable { addr: String },
     Owner {},
+    // None while no ownership transfer is in progress
+    PendingOwner {},
+    IsOwner { addr: String },
+    FeeCollected {},
+    Config {},
+    TotalEarned { addr: String },
+    NativeWithdrawable { addr: String },
+    TokenWithdrawable { token: String, addr: String },
+    AllWithdrawable {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    TokenBalance {},
+    TotalWithdrawable {},
+    PreviewDeposit { amount: Uint128 },
+    Deposit { id: u64 },
+    DepositsByRecipient {
+        addr: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    Depositors {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Operators {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ShareOf { addr: String, amount: Uint128 },
+    FeeInfo {},
+    Stats {},
+    PreviewWithdraw { addr: String, amount: Uint128 },
+    WithdrawableBatch { addrs: Vec<String> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub token: String,
+    pub owner: String,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    // deprecated, the fee in basis points rounded down
+    pub fee_bps: u16,
+    pub split_bps: u16,
+    pub native_denom: Option<String>,
+    pub min_deposit: Uint128,
+    pub rounding: RoundingMode,
+    pub fee_mode: FeeMode,
+    pub withdraw_cooldown: u64,
+    pub max_fee: Option<Uint128>,
+    pub burn_fee: bool,
+    pub max_total_withdrawable: Option<Uint128>,
+    pub remainder_to: RemainderTo,
+    pub recovery_addr: Option<String>,
+    pub decimals: u8,
+    pub symbol: String,
+    pub withdraw_fee_bps: u16,
+    pub forbid_owner_recipient: bool,
+    pub deflationary_token: bool,
+    pub check_contract_balance: bool,
+    pub fee_recipient: String,
+    pub max_recipients: u32,
+    pub group: Option<String>,
+    pub dust_policy: DustPolicy,
+}
+
+// how the deposit fee is rounded when it is not a whole amount
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    #[default]
+    RoundDown,
+    RoundUp,
+}
+
+// fee of a deposit of the configured token, before rounding and the max_fee cap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeInfoResponse {
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    // deprecated, the fee in basis points rounded down
+    pub fee_bps: u16,
+    pub max_fee: Option<Uint128>,
+    pub rounding: RoundingMode,
+}
+
+// lifetime totals of the configured token, the deposit count includes every cw20
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub total_deposited: Uint128,
+    // transferred to recipients, the withdrawal fees excluded
+    pub total_withdrawn: Uint128,
+    // taken on deposits and withdrawals, whether accrued or forwarded
+    pub total_fees_collected: Uint128,
+    pub deposit_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerResponse {
+    pub owner: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawableResponse {
+    pub amount: Uint128,
+}
+
+// what happens to the deposit fees, of the tokens and of the native coin
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeMode {
+    // kept in the contract until withdrawn with WithdrawFee
+    #[default]
+    Accrue,
+    // sent to the recipient with every deposit
+    Forward { recipient: String },
+}
+
+// which address of a deposit receives the extra unit when the split is not a whole amount
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RemainderTo {
+    Addr1,
+    #[default]
+    Addr2,
+}
+
+// where the rounding remainder of a split goes, for every deposit and DistributeBalance
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DustPolicy {
+    // to remainder_to in a pair, to the highest weight in a weighted split, else to the last recipient
+    #[default]
+    LastRecipient,
+    // taken along with the fee, as far as the fee stays within max_fee
+    FeePool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PreviewResponse {
+    pub fee: Uint128,
+    pub amount1: Uint128,
+    pub amount2: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PreviewWithdrawResponse {
+    // transferred to the recipient
+    pub amount: Uint128,
+    // left withdrawable afterwards
+    pub remaining: Uint128,
+}
+
+// data of the withdraw responses
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawResponse {
+    // debited from the balance, the withdrawal fee included
+    pub withdrawn: Uint128,
+    pub remaining: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    SetFee {
+        #[serde(default)]
+        fee_numerator: Option<u64>,
+        #[serde(default)]
+        fee_denominator: Option<u64>,
+        #[serde(default)]
+        fee_bps: Option<u16>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
-    Deposit {
-        addr1: String,
-        addr2: String,
+    Deposit(DepositMsg),
+    DepositMany {
+        recipients: Vec<String>,
+    },
+    DepositWeighted {
+        recipients: Vec<(String, u64)>,
+    },
+    BatchDeposit {
+        deposits: Vec<BatchDepositItem>,
+    },
+    // the amounts must sum to the deposit minus the fee
+    DepositExact {
+        recipients: Vec<(String, Uint128)>,
     },
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositMsg {
+    // without both, the deposit is split by the weights of the members of the configured cw4
+    // group, or by the shares without a group
+    #[serde(default)]
+    pub addr1: Option<String>,
+    #[serde(default)]
+    pub addr2: Option<String>,
+    // credited amounts cannot be withdrawn before this time
+    #[serde(default)]
+    pub unlock_time: Option<Timestamp>,
+    // credited amounts are released linearly between start and end
+    #[serde(default)]
+    pub vesting: Option<VestingSchedule>,
+    // smallest amount each recipient accepts to be credited
+    #[serde(default)]
+    pub min_recipient_amount: Option<Uint128>,
+    // the deposit is rejected once the block time is past it
+    #[serde(default)]
+    pub deadline: Option<Timestamp>,
+    // a retried deposit with the same nonce from the same depositor is rejected
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingSchedule {
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BatchDepositItem {
+    pub amount: Uint128,
+    pub deposit: DepositMsg,
+}
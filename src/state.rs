@@ -1,16 +1,172 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Timestamp, Uint128};
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::msg::{DustPolicy, FeeMode, RemainderTo, RoundingMode};
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct ContractInfo {
     pub token: Addr,
-    pub owner: Addr
+    pub owner: Addr,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub split_bps: u16,
+    pub native_denom: Option<String>,
+    // smallest accepted deposit of the configured token
+    pub min_deposit: Uint128,
+    pub rounding: RoundingMode,
+    pub fee_mode: FeeMode,
+    // seconds a recipient has to wait between two withdrawals, 0 disables it
+    pub withdraw_cooldown: u64,
+    // upper bound of the fee of a single deposit of the configured token
+    pub max_fee: Option<Uint128>,
+    // burn withdrawn fees instead of transferring them, the token must implement cw20 Burn
+    pub burn_fee: bool,
+    // upper bound of the total owed in the configured token, enforced on deposits and owner credits
+    pub max_total_withdrawable: Option<Uint128>,
+    // which address of a deposit receives the rounding remainder of the split
+    pub remainder_to: RemainderTo,
+    // receives revoked balances, they are added to the fees without one
+    pub recovery_addr: Option<Addr>,
+    // display info of the configured token, cached when it is set
+    pub decimals: u8,
+    pub symbol: String,
+    // fee kept on withdrawals of the configured token, in basis points
+    pub withdraw_fee_bps: u16,
+    // reject deposits crediting the owner, keeping fees and payouts apart
+    pub forbid_owner_recipient: bool,
+    // the configured token levies its own transfer fee, deposits credit what was actually received.
+    // BatchDeposit and DepositExact are rejected, withdrawals send the full amount debited.
+    pub deflationary_token: bool,
+    // query the token balance before each withdrawal, failing early when the accounting drifted above it
+    pub check_contract_balance: bool,
+    // receives the withdrawn fees when no other recipient is given, the owner at instantiation
+    pub fee_recipient: Addr,
+    // longest recipient list a single deposit accepts
+    pub max_recipients: u32,
+    // cw4 group splitting the deposits that name no recipients by member weight, instead of the shares
+    pub group: Option<Addr>,
+    // where the rounding remainder of an even split goes
+    pub dust_policy: DustPolicy,
 }
 
 pub const CONTRACT_INFO: Item<ContractInfo> = Item::new("token_distributor");
 
+// config of the first release, which saved no cw2 version, only read by migrate
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct LegacyContractInfo {
+    pub token: Addr,
+    pub owner: Addr,
+}
+
+pub const LEGACY_CONTRACT_INFO: Item<LegacyContractInfo> = Item::new("token_distributor");
+
+pub const PENDING_OWNER: Item<Addr> = Item::new("pending_owner");
+
+// deposit nonces already processed, keyed by (depositor, nonce)
+pub const NONCES: Map<(Addr, &str), bool> = Map::new("nonces");
+
+// senders allowed to deposit, anyone can deposit while it is empty
+pub const DEPOSITORS: Map<Addr, bool> = Map::new("depositors");
+
+// time of the last withdrawal of each recipient, for the withdrawal cooldown
+pub const LAST_WITHDRAWAL: Map<Addr, Timestamp> = Map::new("last_withdrawal");
+
+// fee and minimum deposit of an accepted token, overriding the ones of the contract
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TokenConfig {
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub min_deposit: Uint128,
+    pub enabled: bool,
+}
+
+pub const TOKEN_CONFIGS: Map<Addr, TokenConfig> = Map::new("token_configs");
+
+// weights of the shareholders splitting the deposits that name no recipients, without a group
+pub const SHARES: Map<Addr, u64> = Map::new("shares");
+
+// addresses allowed to trigger withdrawals on behalf of recipients
+pub const OPERATORS: Map<Addr, bool> = Map::new("operators");
+
+// accounts the owner blocked from withdrawing, they keep being credited
+pub const FROZEN: Map<Addr, bool> = Map::new("frozen");
+
 pub const WITHDRAWABLE: Map<Addr, Uint128> = Map::new("withdrawable");
 
+// lifetime amount credited to each recipient, withdrawals do not decrease it
+pub const TOTAL_EARNED: Map<Addr, Uint128> = Map::new("total_earned");
+
 pub const FEE_COLLECTED: Item<Uint128> = Item::new("fee_collected");
+
+// running total owed to recipients in the configured token, locked and vesting amounts included
+pub const TOTAL_WITHDRAWABLE: Item<Uint128> = Item::new("total_withdrawable");
+
+// lifetime totals of the configured token for monitoring: the amount deposited, transferred
+// to recipients by withdrawals and taken as deposit and withdrawal fees
+pub const TOTAL_DEPOSITED: Item<Uint128> = Item::new("total_deposited");
+
+pub const TOTAL_WITHDRAWN: Item<Uint128> = Item::new("total_withdrawn");
+
+pub const TOTAL_FEES: Item<Uint128> = Item::new("total_fees");
+
+// number of cw20 deposits so far, the last one has this id
+pub const DEPOSIT_COUNT: Item<u64> = Item::new("deposit_count");
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct DepositRecord {
+    pub sender: Addr,
+    pub token: Addr,
+    pub amount: Uint128,
+    pub fee: Uint128,
+    pub time: Timestamp,
+}
+
+pub const DEPOSITS: Map<u64, DepositRecord> = Map::new("deposits");
+
+// amount each cw20 deposit credited to a recipient, keyed by (recipient, deposit id)
+pub const RECIPIENT_DEPOSITS: Map<(Addr, u64), Uint128> = Map::new("recipient_deposits");
+
+// time-locked amounts of the configured token, keyed by (recipient, unlock time in seconds)
+pub const LOCKED: Map<(Addr, u64), Uint128> = Map::new("locked");
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VestingEntry {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub total: Uint128,
+    // part already moved into the withdrawable balance
+    pub released: Uint128,
+}
+
+// linearly vesting amounts of the configured token per recipient
+pub const VESTING: Map<Addr, Vec<VestingEntry>> = Map::new("vesting");
+
+// balances and fees of native deposits, keyed by denom so they never mix with the cw20 accounting
+pub const NATIVE_WITHDRAWABLE: Map<(&str, Addr), Uint128> = Map::new("native_withdrawable");
+
+pub const NATIVE_FEE_COLLECTED: Map<&str, Uint128> = Map::new("native_fee_collected");
+
+// balances and fees of cw20 tokens other than the configured one, keyed by token address
+pub const TOKEN_WITHDRAWABLE: Map<(Addr, Addr), Uint128> = Map::new("token_withdrawable");
+
+pub const TOKEN_FEE_COLLECTED: Map<Addr, Uint128> = Map::new("token_fee_collected");
+
+// withdrawals waiting for the result of their token transfer, keyed by reply id
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PendingWithdrawal {
+    pub token: Addr,
+    pub account: Addr,
+    // amount transferred, the withdrawal fee excluded
+    pub amount: Uint128,
+    #[serde(default)]
+    pub fee: Uint128,
+    // withdrawal time of the account before this one, restored if the transfer fails
+    #[serde(default)]
+    pub last_withdrawal: Option<Timestamp>,
+}
+
+pub const PENDING_WITHDRAWALS: Map<u64, PendingWithdrawal> = Map::new("pending_withdrawals");
+
+pub const LAST_REPLY_ID: Item<u64> = Item::new("last_reply_id");
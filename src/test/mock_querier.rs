@@ -7,15 +7,21 @@ use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::panic;
 
-use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw4::{Cw4QueryMsg, Member, MemberListResponse};
+use cw20::{
+    AllowanceResponse, BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, Expiration, MinterResponse,
+    TokenInfoResponse,
+};
 
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
 /// this uses our CustomQuerier.
+/// The default test token asset0001 is registered so that it passes the cw20 probe of instantiate.
 pub fn mock_dependencies(
     contract_balance: &[Coin],
 ) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
-    let custom_querier: WasmMockQuerier =
+    let mut custom_querier: WasmMockQuerier =
         WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)]));
+    custom_querier.with_token_balances(&[(&"asset0001".to_string(), &[])]);
 
     OwnedDeps {
         storage: MockStorage::default(),
@@ -28,20 +34,20 @@ pub fn mock_dependencies(
 pub struct WasmMockQuerier {
     base: MockQuerier,
     token_querier: TokenQuerier,
+    // members of each cw4 group contract, sorted by address
+    groups: HashMap<String, Vec<Member>>,
+    // largest page the groups return whatever the requested limit
+    group_page_limit: Option<u32>,
 }
 
 #[derive(Clone, Default)]
 pub struct TokenQuerier {
     // this lets us iterate over all pairs that match the first string
     balances: HashMap<String, HashMap<String, Uint128>>,
-}
-
-impl TokenQuerier {
-    pub fn new(balances: &[(&String, &[(&String, &Uint128)])]) -> Self {
-        TokenQuerier {
-            balances: balances_to_map(balances),
-        }
-    }
+    // minter of each token contract, tokens without one are not mintable
+    minters: HashMap<String, MinterResponse>,
+    // allowances of each token contract keyed by (owner, spender)
+    allowances: HashMap<String, HashMap<(String, String), AllowanceResponse>>,
 }
 
 pub(crate) fn balances_to_map(
@@ -78,6 +84,26 @@ impl Querier for WasmMockQuerier {
 impl WasmMockQuerier {
     pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
         match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) if self.groups.contains_key(contract_addr) => {
+                let members = &self.groups[contract_addr];
+                match from_binary(msg).unwrap() {
+                    Cw4QueryMsg::ListMembers { start_after, limit } => {
+                        // map_or rather than is_none_or, which needs rust 1.82
+                        #[allow(clippy::unnecessary_map_or)]
+                        let members = members
+                            .iter()
+                            .filter(|member| start_after.as_ref().map_or(true, |start| member.addr > *start))
+                            .take(limit.unwrap_or(10).min(self.group_page_limit.unwrap_or(u32::MAX)) as usize)
+                            .cloned()
+                            .collect();
+
+                        SystemResult::Ok(ContractResult::Ok(
+                            to_binary(&MemberListResponse { members }).unwrap(),
+                        ))
+                    }
+                    _ => panic!("DO NOT ENTER HERE"),
+                }
+            }
             QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => match from_binary(msg).unwrap() {
                 Cw20QueryMsg::TokenInfo {} => {
                     let balances: &HashMap<String, Uint128> =
@@ -94,10 +120,11 @@ impl WasmMockQuerier {
                             }
                         };
 
+                    // capped at Uint128::MAX so that huge balances do not overflow
                     let mut total_supply = Uint128::zero();
 
                     for balance in balances {
-                        total_supply += *balance.1;
+                        total_supply = total_supply.checked_add(*balance.1).unwrap_or(Uint128::MAX);
                     }
 
                     SystemResult::Ok(ContractResult::Ok(
@@ -142,6 +169,24 @@ impl WasmMockQuerier {
                     ))
                 }
 
+                Cw20QueryMsg::Minter {} => SystemResult::Ok(ContractResult::Ok(
+                    to_binary(&self.token_querier.minters.get(contract_addr)).unwrap(),
+                )),
+                Cw20QueryMsg::Allowance { owner, spender } => {
+                    let allowance = self
+                        .token_querier
+                        .allowances
+                        .get(contract_addr)
+                        .and_then(|allowances| allowances.get(&(owner, spender)))
+                        .cloned()
+                        .unwrap_or(AllowanceResponse {
+                            allowance: Uint128::zero(),
+                            expires: Expiration::Never {},
+                        });
+
+                    SystemResult::Ok(ContractResult::Ok(to_binary(&allowance).unwrap()))
+                }
+
                 _ => panic!("DO NOT ENTER HERE"),
             },
             _ => self.base.handle_query(request),
@@ -154,12 +199,49 @@ impl WasmMockQuerier {
         WasmMockQuerier {
             base,
             token_querier: TokenQuerier::default(),
+            groups: HashMap::new(),
+            group_page_limit: None,
         }
     }
 
     // configure the mint whitelist mock querier
     pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
-        self.token_querier = TokenQuerier::new(balances);
+        self.token_querier.balances = balances_to_map(balances);
+    }
+
+    // configure the minter answered for a token contract
+    pub fn with_minter(&mut self, token: &str, minter: &str, cap: Option<Uint128>) {
+        self.token_querier.minters.insert(
+            token.to_string(),
+            MinterResponse {
+                minter: minter.to_string(),
+                cap,
+            },
+        );
+    }
+
+    // configure the members and weights of a cw4 group contract
+    pub fn with_group_members(&mut self, group: &str, members: &[(&str, u64)]) {
+        let mut members: Vec<Member> = members
+            .iter()
+            .map(|(addr, weight)| Member { addr: addr.to_string(), weight: *weight })
+            .collect();
+        members.sort_by(|a, b| a.addr.cmp(&b.addr));
+        self.groups.insert(group.to_string(), members);
+    }
+
+    // cap the pages of the groups below the requested limit
+    pub fn with_group_page_limit(&mut self, limit: u32) {
+        self.group_page_limit = Some(limit);
+    }
+
+    // configure the allowance of spender over the tokens of owner
+    pub fn with_allowance(&mut self, token: &str, owner: &str, spender: &str, allowance: Uint128, expires: Expiration) {
+        self.token_querier
+            .allowances
+            .entry(token.to_string())
+            .or_default()
+            .insert((owner.to_string(), spender.to_string()), AllowanceResponse { allowance, expires });
     }
 }
 
@@ -222,12 +304,123 @@ mod mock_exception {
         )
     }
 
+    #[test]
+    fn huge_tokens_info_total_supply_is_capped() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_token_balances(&[(
+            &"token0000".to_string(),
+            &[
+                (&"address0000".to_string(), &(Uint128::MAX - Uint128::from(1u128))),
+                (&"address0001".to_string(), &Uint128::from(2u128)),
+            ],
+        )]);
+
+        let msg = to_binary(&Cw20QueryMsg::TokenInfo {}).unwrap();
+
+        let res = deps
+            .querier
+            .handle_query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "token0000".to_string(),
+                msg,
+            }))
+            .unwrap()
+            .unwrap();
+        let token_info: TokenInfoResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::MAX, token_info.total_supply);
+    }
+
+    #[test]
+    fn tokens_minter() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_minter("token0000", "minter0000", Some(Uint128::from(1000u128)));
+
+        let msg = to_binary(&Cw20QueryMsg::Minter {}).unwrap();
+
+        let res = deps
+            .querier
+            .handle_query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "token0000".to_string(),
+                msg: msg.clone(),
+            }))
+            .unwrap()
+            .unwrap();
+        let minter: Option<MinterResponse> = from_binary(&res).unwrap();
+        assert_eq!(
+            Some(MinterResponse {
+                minter: "minter0000".to_string(),
+                cap: Some(Uint128::from(1000u128)),
+            }),
+            minter
+        );
+
+        // tokens without a minter are not mintable
+        let res = deps
+            .querier
+            .handle_query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "token0001".to_string(),
+                msg,
+            }))
+            .unwrap()
+            .unwrap();
+        let minter: Option<MinterResponse> = from_binary(&res).unwrap();
+        assert_eq!(None, minter);
+    }
+
+    #[test]
+    fn tokens_allowance() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_allowance("token0000", "address0000", "address0001", Uint128::from(100u128), Expiration::Never {});
+
+        let query_allowance = |owner: &str| -> AllowanceResponse {
+            let msg = to_binary(&Cw20QueryMsg::Allowance {
+                owner: owner.to_string(),
+                spender: "address0001".to_string(),
+            })
+            .unwrap();
+            let res = deps
+                .querier
+                .handle_query(&QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: "token0000".to_string(),
+                    msg,
+                }))
+                .unwrap()
+                .unwrap();
+            from_binary(&res).unwrap()
+        };
+
+        assert_eq!(Uint128::from(100u128), query_allowance("address0000").allowance);
+        assert_eq!(Uint128::zero(), query_allowance("address0002").allowance);
+    }
+
+    #[test]
+    fn group_list_members() {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_group_members("group0000", &[("address0001", 3), ("address0000", 1)]);
+
+        let msg = to_binary(&Cw4QueryMsg::ListMembers {
+            start_after: Some("address0000".to_string()),
+            limit: None,
+        })
+        .unwrap();
+
+        let res = deps
+            .querier
+            .handle_query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "group0000".to_string(),
+                msg,
+            }))
+            .unwrap()
+            .unwrap();
+        let members: MemberListResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Member { addr: "address0001".to_string(), weight: 3 }], members.members);
+    }
+
     #[test]
     #[should_panic]
-    fn none_tokens_minter_will_panic() {
+    fn none_tokens_marketing_info_will_panic() {
         let deps = mock_dependencies(&[]);
 
-        let msg = to_binary(&Cw20QueryMsg::Minter {}).unwrap();
+        let msg = to_binary(&Cw20QueryMsg::MarketingInfo {}).unwrap();
 
         deps.querier
             .handle_query(&QueryRequest::Wasm(WasmQuery::Smart {
@@ -1,40 +1,70 @@
-use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, to_binary, CosmosMsg, WasmMsg, SubMsg, Uint128,
+    attr, coins, from_binary, from_slice, Addr, to_binary, to_vec, BankMsg, Binary, CosmosMsg, Event, Reply, StdError, WasmMsg, SubMsg,
+    SubMsgResponse, SubMsgResult, Uint128,
 };
 
-use crate::contract::{instantiate, execute, query};
-use crate::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, Cw20HookMsg};
+use crate::contract::{clamp_limit, compute_split, instantiate, execute, migrate, query, reply, sudo, CONTRACT_NAME, CONTRACT_VERSION};
+use crate::msg::{BatchDepositItem, ConfigResponse, DepositMsg, DustPolicy, InstantiateMsg, ExecuteMsg, FeeInfoResponse, FeeMode, MigrateMsg, OwnerResponse, PreviewResponse, PreviewWithdrawResponse, QueryMsg, RemainderTo, RoundingMode, StatsResponse, SudoMsg, VestingSchedule, WithdrawResponse, WithdrawableResponse, Cw20HookMsg};
 use crate::error::{ContractError};
+use crate::state::{DepositRecord, LegacyContractInfo, FEE_COLLECTED, LEGACY_CONTRACT_INFO, TOTAL_WITHDRAWABLE};
+use cw2::{get_contract_version, set_contract_version};
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
 use crate::test::mock_querier::mock_dependencies;
 
+fn default_instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        token: "asset0001".to_string(),
+        owner: "addr0000".to_string(),
+        fee_numerator: Some(50),
+        fee_denominator: Some(1000),
+        fee_bps: None,
+        split_bps: 5000,
+        native_denom: None,
+        min_deposit: None,
+        rounding: None,
+        fee_mode: None,
+        withdraw_cooldown: None,
+        max_fee: None,
+        burn_fee: None,
+        max_total_withdrawable: None,
+        shareholders: None,
+        remainder_to: None,
+        recovery_addr: None,
+        withdraw_fee_bps: None,
+        forbid_owner_recipient: None,
+        deflationary_token: None,
+        check_contract_balance: None,
+        max_recipients: None,
+        group: None,
+        dust_policy: None,
+    }
+}
+
 #[test]
 fn proper_initialization() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg {
-        token: "asset0001".to_string(),
-        owner: "addr0000".to_string(),
-    };
+    let msg = default_instantiate_msg();
 
     let info = mock_info("addr0000", &[]);
 
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let owner: String = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Owner{}).unwrap()).unwrap();
+    let owner: String = from_binary::<OwnerResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Owner{}).unwrap()).unwrap().owner;
     assert_eq!("addr0000".to_string(), owner);
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!("crates.io:cosmwasm-token-distributor", version.contract);
+    assert_eq!(env!("CARGO_PKG_VERSION"), version.version);
 }
 
 #[test]
 fn execute_deposit() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg {
-        token: "asset0001".to_string(),
-        owner: "addr0000".to_string(),
-    };
+    let msg = default_instantiate_msg();
 
     let info = mock_info("addr0000", &[]);
 
@@ -47,10 +77,15 @@ fn execute_deposit() {
 
     let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0000".to_string(),
-        msg: to_binary(&Cw20HookMsg::Deposit{
-            addr1: "addr0002".to_string(),
-            addr2: "addr0003".to_string(),
-        }).unwrap(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
         amount: Uint128::from(100u128),
     });
 
@@ -58,9 +93,9 @@ fn execute_deposit() {
 
     execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
 
-    let withdrawable1: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    let withdrawable1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
     assert_eq!(Uint128::from(47u128), withdrawable1);
-    let withdrawable2: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap();
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
     assert_eq!(Uint128::from(48u128), withdrawable2);
 }
 
@@ -68,10 +103,7 @@ fn execute_deposit() {
 fn execute_withdraw() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg {
-        token: "asset0001".to_string(),
-        owner: "addr0000".to_string(),
-    };
+    let msg = default_instantiate_msg();
 
     let info = mock_info("addr0000", &[]);
 
@@ -86,10 +118,15 @@ fn execute_withdraw() {
 
     let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0000".to_string(),
-        msg: to_binary(&Cw20HookMsg::Deposit{
-            addr1: "addr0002".to_string(),
-            addr2: "addr0003".to_string(),
-        }).unwrap(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
         amount: Uint128::from(1000u128),
     });
 
@@ -97,18 +134,21 @@ fn execute_withdraw() {
 
     execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
 
-    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128) };
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
 
     let withdraw_info = mock_info("addr0002", &[]);
 
     let res = execute(deps.as_mut(), mock_env(), withdraw_info, withdraw_msg).unwrap();
 
-    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
     assert_eq!(Uint128::from(175u128), withdrawable);
 
-    let msg_transfer = res.messages.get(0).expect("no message");
+    let data: WithdrawResponse = from_binary(&res.data.clone().unwrap()).unwrap();
+    assert_eq!(WithdrawResponse { withdrawn: Uint128::from(300u128), remaining: Uint128::from(175u128) }, data);
+
+    let msg_transfer = res.messages.first().expect("no message");
     assert_eq!(
-        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        &SubMsg::reply_always(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "asset0001".to_string(),
             msg: to_binary(&Cw20ExecuteMsg::Transfer {
                 recipient: "addr0002".to_string(),
@@ -116,19 +156,125 @@ fn execute_withdraw() {
             })
             .unwrap(),
             funds: vec![],
-        })),
+        }), 1),
         msg_transfer,
     );
 }
 
+#[test]
+fn execute_withdraw_percent() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::WithdrawPercent{ bps: 10001 }).unwrap_err();
+    match res {
+        ContractError::InvalidPercent {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::WithdrawPercent{ bps: 5000 }).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0001".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr0002".to_string(),
+                    amount: Uint128::from(237u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            1,
+        )]
+    );
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(238u128), withdrawable);
+}
+
+#[test]
+fn execute_freeze_account() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let freeze_msg = ExecuteMsg::FreezeAccount{ addr: "addr0002".to_string() };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), freeze_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), freeze_msg).unwrap();
+
+    // a frozen account is still credited
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg.clone()).unwrap_err();
+    match res {
+        ContractError::AccountFrozen {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::WithdrawAll{}).unwrap_err();
+    match res {
+        ContractError::AccountFrozen {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::UnfreezeAccount{ addr: "addr0002".to_string() }).unwrap();
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+}
+
 #[test]
 fn execute_withdraw_all() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg {
-        token: "asset0001".to_string(),
-        owner: "addr0000".to_string(),
-    };
+    let msg = default_instantiate_msg();
 
     let info = mock_info("addr0000", &[]);
 
@@ -143,10 +289,15 @@ fn execute_withdraw_all() {
 
     let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0000".to_string(),
-        msg: to_binary(&Cw20HookMsg::Deposit{
-            addr1: "addr0002".to_string(),
-            addr2: "addr0003".to_string(),
-        }).unwrap(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
         amount: Uint128::from(1000u128),
     });
 
@@ -160,12 +311,12 @@ fn execute_withdraw_all() {
 
     let res = execute(deps.as_mut(), mock_env(), withdraw_info, withdraw_msg).unwrap();
 
-    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
     assert_eq!(Uint128::zero(), withdrawable);
 
-    let msg_transfer = res.messages.get(0).expect("no message");
+    let msg_transfer = res.messages.first().expect("no message");
     assert_eq!(
-        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        &SubMsg::reply_always(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "asset0001".to_string(),
             msg: to_binary(&Cw20ExecuteMsg::Transfer {
                 recipient: "addr0002".to_string(),
@@ -173,7 +324,7 @@ fn execute_withdraw_all() {
             })
             .unwrap(),
             funds: vec![],
-        })),
+        }), 1),
         msg_transfer,
     );
 }
@@ -182,10 +333,7 @@ fn execute_withdraw_all() {
 fn execute_withdraw_fee() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg {
-        token: "asset0001".to_string(),
-        owner: "addr0000".to_string(),
-    };
+    let msg = default_instantiate_msg();
 
     let info = mock_info("addr0000", &[]);
 
@@ -200,10 +348,15 @@ fn execute_withdraw_fee() {
 
     let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0000".to_string(),
-        msg: to_binary(&Cw20HookMsg::Deposit{
-            addr1: "addr0002".to_string(),
-            addr2: "addr0003".to_string(),
-        }).unwrap(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
         amount: Uint128::from(1000u128),
     });
 
@@ -211,7 +364,7 @@ fn execute_withdraw_fee() {
 
     execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
 
-    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{};
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
 
     let withdraw_fee_info = mock_info("addr0001", &[]);
 
@@ -225,7 +378,7 @@ fn execute_withdraw_fee() {
 
     let res = execute(deps.as_mut(), mock_env(), withdraw_fee_info, withdraw_fee_msg).unwrap();
 
-    let msg_transfer = res.messages.get(0).expect("no message");
+    let msg_transfer = res.messages.first().expect("no message");
     assert_eq!(
         &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "asset0001".to_string(),
@@ -239,3 +392,5354 @@ fn execute_withdraw_fee() {
         msg_transfer,
     );
 }
+
+#[test]
+fn execute_deposit_custom_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_numerator: Some(25),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(487u128), withdrawable1);
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(488u128), withdrawable2);
+}
+
+#[test]
+fn instantiate_invalid_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_numerator: Some(1001),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    match res {
+        ContractError::InvalidFee {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // in basis points, 100% is the highest fee
+    let msg = InstantiateMsg {
+        fee_numerator: Some(10001),
+        fee_denominator: Some(10000),
+        ..default_instantiate_msg()
+    };
+
+    let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    match res {
+        ContractError::InvalidFee {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // a lone numerator or denominator is not combined with the default
+    let msg = InstantiateMsg {
+        fee_numerator: Some(50),
+        fee_denominator: None,
+        ..default_instantiate_msg()
+    };
+
+    let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    match res {
+        ContractError::InvalidFee {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = InstantiateMsg {
+        fee_numerator: None,
+        fee_denominator: Some(10000),
+        ..default_instantiate_msg()
+    };
+
+    let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    match res {
+        ContractError::InvalidFee {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = InstantiateMsg {
+        fee_numerator: Some(10000),
+        fee_denominator: Some(10000),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn query_fee_collected() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let fee: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), fee);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let fee: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee);
+}
+
+#[test]
+fn execute_deposit_custom_split() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        split_bps: 7000,
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(665u128), withdrawable1);
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(285u128), withdrawable2);
+}
+
+#[test]
+fn instantiate_invalid_split() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        split_bps: 10001,
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Invalid split"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn instantiate_owner_is_token() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "asset0001".to_string(),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    match res {
+        ContractError::OwnerIsToken {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn instantiate_invalid_token() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        token: "addr0009".to_string(),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    match res {
+        ContractError::InvalidToken { addr } => assert_eq!(addr, "addr0009"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_many() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{
+            recipients: vec![
+                "addr0002".to_string(),
+                "addr0003".to_string(),
+                "addr0004".to_string(),
+            ],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(316u128), withdrawable1);
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(316u128), withdrawable2);
+    let withdrawable3: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0004".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(318u128), withdrawable3);
+}
+
+#[test]
+fn execute_deposit_many_dust_to_fee_pool() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        dust_policy: Some(DustPolicy::FeePool),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 950 after the fee does not divide by 3
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{
+            recipients: vec!["addr0002".to_string(), "addr0003".to_string(), "addr0004".to_string()],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("fee", "52"), res.attributes[2]);
+
+    for addr in ["addr0002", "addr0003", "addr0004"] {
+        let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: addr.to_string() }).unwrap()).unwrap().amount;
+        assert_eq!(Uint128::from(316u128), withdrawable);
+    }
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(52u128), fee_collected);
+}
+
+#[test]
+fn execute_dust_to_fee_pool() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        dust_policy: Some(DustPolicy::FeePool),
+        native_denom: Some("uatom".to_string()),
+        shareholders: Some(vec![
+            ("addr0007".to_string(), 1),
+            ("addr0008".to_string(), 1),
+            ("addr0009".to_string(), 1),
+        ]),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // 951 after the fee does not split evenly between addr0002 and addr0003
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1001u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("fee", "51"), res.attributes[2]);
+
+    // 950 after the fee does not divide by 3
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositWeighted{
+            recipients: vec![("addr0004".to_string(), 1), ("addr0005".to_string(), 1), ("addr0006".to_string(), 1)],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("fee", "52"), res.attributes[2]);
+
+    // split by the shares
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: None,
+            addr2: None,
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("fee", "52"), res.attributes[2]);
+
+    for (addr, amount) in [
+        ("addr0002", 475u128), ("addr0003", 475),
+        ("addr0004", 316), ("addr0005", 316), ("addr0006", 316),
+        ("addr0007", 316), ("addr0008", 316), ("addr0009", 316),
+    ] {
+        let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: addr.to_string() }).unwrap()).unwrap().amount;
+        assert_eq!(Uint128::from(amount), withdrawable);
+    }
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(155u128), fee_collected);
+
+    // 301 beyond what the contract owes does not split evenly between two recipients
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(3302u128))],
+    )]);
+    let distribute_msg = ExecuteMsg::DistributeBalance{ recipients: vec!["addr0004".to_string(), "addr0005".to_string()] };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), distribute_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0005".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(466u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(156u128), fee_collected);
+
+    // native deposits pool their dust with the native fee
+    let deposit_msg = ExecuteMsg::DepositNative(DepositMsg {
+        addr1: Some("addr0002".to_string()),
+        addr2: Some("addr0003".to_string()),
+        unlock_time: None,
+        vesting: None,
+        min_recipient_amount: None,
+        deadline: None,
+        nonce: None,
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &coins(1001u128, "uatom")), deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::NativeWithdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(475u128), withdrawable);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::WithdrawNativeFee{ recipient: None }).unwrap();
+    assert_eq!(
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: coins(51u128, "uatom"),
+        })),
+        res.messages[0],
+    );
+
+    // the group split pools its dust as well
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_group_members("group0000", &[("addr0002", 1), ("addr0003", 1), ("addr0004", 1)]);
+
+    let msg = InstantiateMsg {
+        dust_policy: Some(DustPolicy::FeePool),
+        group: Some("group0000".to_string()),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: None,
+            addr2: None,
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("fee", "52"), res.attributes[2]);
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(316u128), withdrawable);
+}
+
+#[test]
+fn execute_dust_to_fee_pool_max_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        dust_policy: Some(DustPolicy::FeePool),
+        max_fee: Some(Uint128::from(51u128)),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // the fee of 50 leaves room for a single unit of dust
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositWeighted{
+            recipients: vec![("addr0002".to_string(), 1), ("addr0003".to_string(), 1), ("addr0004".to_string(), 1)],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("fee", "51"), res.attributes[2]);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{
+            recipients: vec!["addr0002".to_string(), "addr0003".to_string(), "addr0004".to_string()],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("fee", "51"), res.attributes[2]);
+
+    // the rest of the dust goes to the recipients
+    for (addr, amount) in [("addr0002", 633u128), ("addr0003", 632), ("addr0004", 633)] {
+        let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: addr.to_string() }).unwrap()).unwrap().amount;
+        assert_eq!(Uint128::from(amount), withdrawable);
+    }
+
+    // the fee of 1020 is already at the cap, the dust stays with addr2
+    let (fee, amount1, amount2) = compute_split(Uint128::from(1020u128), 50, 1000, 5000, RoundingMode::RoundDown, Some(Uint128::from(51u128)), RemainderTo::Addr2, DustPolicy::FeePool).unwrap();
+    assert_eq!((51, 484, 485), (fee.u128(), amount1.u128(), amount2.u128()));
+}
+
+#[test]
+fn execute_deposit_many_empty() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{ recipients: vec![] }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info.clone(), deposit_msg).unwrap_err();
+    match res {
+        ContractError::EmptyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositExact{ recipients: vec![] }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap_err();
+    match res {
+        ContractError::EmptyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let distribute_msg = ExecuteMsg::DistributeBalance{ recipients: vec![] };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), distribute_msg).unwrap_err();
+    match res {
+        ContractError::EmptyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_weighted() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositWeighted{
+            recipients: vec![
+                ("addr0002".to_string(), 1),
+                ("addr0003".to_string(), 2),
+                ("addr0004".to_string(), 1),
+            ],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(237u128), withdrawable1);
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(476u128), withdrawable2);
+    let withdrawable3: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0004".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(237u128), withdrawable3);
+}
+
+#[test]
+fn execute_deposit_many_max_recipients() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        max_recipients: Some(3),
+        shareholders: Some(vec![
+            ("addr0002".to_string(), 1),
+            ("addr0003".to_string(), 1),
+            ("addr0004".to_string(), 1),
+            ("addr0005".to_string(), 1),
+        ]),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let recipients: Vec<String> = (2..6).map(|i| format!("addr000{}", i)).collect();
+
+    // at the limit
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{ recipients: recipients[..3].to_vec() }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{ recipients }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::TooManyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // two entries of a batch credit four recipients
+    let item = BatchDepositItem {
+        amount: Uint128::from(500u128),
+        deposit: DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        },
+    };
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::BatchDeposit{ deposits: vec![item.clone(), item] }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::TooManyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // four shareholders
+    let shares_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: None,
+            addr2: None,
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), shares_msg.clone()).unwrap_err();
+    match res {
+        ContractError::TooManyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // four group members
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_group_members("group0000", &[("addr0002", 1), ("addr0003", 1), ("addr0004", 1), ("addr0005", 1)]);
+
+    let msg = InstantiateMsg {
+        max_recipients: Some(3),
+        group: Some("group0000".to_string()),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), shares_msg).unwrap_err();
+    match res {
+        ContractError::TooManyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_group() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_group_members("group0000", &[("addr0002", 1), ("addr0003", 3), ("addr0004", 0)]);
+
+    let msg = InstantiateMsg {
+        group: Some("group0000".to_string()),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: None,
+            addr2: None,
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+
+    // 950 after the fee, split 1:3
+    let withdrawable1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(237u128), withdrawable1);
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(713u128), withdrawable2);
+    let withdrawable3: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0004".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable3);
+
+    // pages shorter than the requested limit do not end the listing, the group takes precedence
+    // over the shares
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_group_members("group0000", &[("addr0002", 1), ("addr0003", 3), ("addr0004", 0)]);
+    deps.querier.with_group_page_limit(1);
+
+    let msg = InstantiateMsg {
+        group: Some("group0000".to_string()),
+        shareholders: Some(vec![("addr0005".to_string(), 1)]),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(713u128), withdrawable2);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0005".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+
+    // members without weight count towards the members read, so that paging stays bounded
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_group_members("group0000", &[
+        ("addr0002", 0), ("addr0003", 0), ("addr0004", 0), ("addr0005", 0), ("addr0006", 0),
+        ("addr0007", 0), ("addr0008", 0), ("addr0009", 0), ("addr0010", 1),
+    ]);
+
+    let msg = InstantiateMsg {
+        group: Some("group0000".to_string()),
+        max_recipients: Some(2),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap_err();
+    match res {
+        ContractError::TooManyRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // without a group nor shares there is nothing to split by
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::NoRecipients {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_exact() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 950 left after the fee
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositExact{
+            recipients: vec![
+                ("addr0002".to_string(), Uint128::from(100u128)),
+                ("addr0003".to_string(), Uint128::from(850u128)),
+            ],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdrawable1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(100u128), withdrawable1);
+    let withdrawable2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(850u128), withdrawable2);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositExact{
+            recipients: vec![
+                ("addr0002".to_string(), Uint128::from(100u128)),
+                ("addr0003".to_string(), Uint128::from(900u128)),
+            ],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::ExactAmountMismatch { expected, actual } => {
+            assert_eq!(Uint128::from(950u128), expected);
+            assert_eq!(Uint128::from(1000u128), actual);
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_weighted_zero_weight() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositWeighted{
+            recipients: vec![
+                ("addr0002".to_string(), 0),
+                ("addr0003".to_string(), 0),
+            ],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap_err();
+    match res {
+        ContractError::ZeroTotalWeight {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_transfer_ownership() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let transfer_msg = ExecuteMsg::TransferOwnership{ new_owner: "addr0001".to_string() };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), transfer_msg.clone()).unwrap_err();
+    match res {
+        ContractError::Unauthorized {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let pending_owner: Option<String> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PendingOwner{}).unwrap()).unwrap();
+    assert_eq!(None, pending_owner);
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), transfer_msg).unwrap();
+
+    // ownership does not change until accepted
+    let owner: String = from_binary::<OwnerResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Owner{}).unwrap()).unwrap().owner;
+    assert_eq!("addr0000".to_string(), owner);
+    let pending_owner: Option<String> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PendingOwner{}).unwrap()).unwrap();
+    assert_eq!(Some("addr0001".to_string()), pending_owner);
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), ExecuteMsg::AcceptOwnership{}).unwrap();
+
+    let owner: String = from_binary::<OwnerResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Owner{}).unwrap()).unwrap().owner;
+    assert_eq!("addr0001".to_string(), owner);
+    let pending_owner: Option<String> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PendingOwner{}).unwrap()).unwrap();
+    assert_eq!(None, pending_owner);
+
+    // the pending transfer is consumed on acceptance
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), ExecuteMsg::AcceptOwnership{}).unwrap_err();
+    match res {
+        ContractError::Unauthorized {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_accept_ownership_not_pending() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let transfer_msg = ExecuteMsg::TransferOwnership{ new_owner: "addr0001".to_string() };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), transfer_msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::AcceptOwnership{}).unwrap_err();
+    match res {
+        ContractError::Unauthorized {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let owner: String = from_binary::<OwnerResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Owner{}).unwrap()).unwrap().owner;
+    assert_eq!("addr0000".to_string(), owner);
+}
+
+#[test]
+fn execute_deposit_same_address() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0002".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(950u128), withdrawable);
+}
+
+#[test]
+fn migrate_bumps_version() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // simulate a contract instantiated from older code
+    set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.0.1").unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(CONTRACT_NAME, version.contract);
+    assert_eq!(CONTRACT_VERSION, version.version);
+}
+
+#[test]
+fn migrate_from_first_release() {
+    let mut deps = mock_dependencies(&[]);
+
+    // storage of the first release: a config of the token and owner only, and no cw2 version
+    let legacy = LegacyContractInfo {
+        token: Addr::unchecked("asset0001"),
+        owner: Addr::unchecked("addr0000"),
+    };
+    LEGACY_CONTRACT_INFO.save(deps.as_mut().storage, &legacy).unwrap();
+    FEE_COLLECTED.save(deps.as_mut().storage, &Uint128::from(50u128)).unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(CONTRACT_NAME, version.contract);
+    assert_eq!(CONTRACT_VERSION, version.version);
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!("asset0001", config.token);
+    assert_eq!("addr0000", config.owner);
+    assert_eq!("addr0000", config.fee_recipient);
+    assert_eq!((50, 1000, 5000), (config.fee_numerator, config.fee_denominator, config.split_bps));
+
+    // deposits keep the fee and split of the first release
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(100u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(47u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(48u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(55u128), fee_collected);
+}
+
+#[test]
+fn migrate_backfills_total_withdrawable() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let start = mock_env().block.time;
+    for (unlock_time, vesting) in [(Some(start.plus_seconds(3600)), None), (None, Some(VestingSchedule { start, end: start.plus_seconds(1000) }))] {
+        let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0000".to_string(),
+            msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+                addr1: Some("addr0002".to_string()),
+                addr2: Some("addr0003".to_string()),
+                unlock_time,
+                vesting,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            })).unwrap(),
+            amount: Uint128::from(1000u128),
+        });
+        execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    }
+
+    // simulate a contract migrated from code without the running total
+    TOTAL_WITHDRAWABLE.remove(deps.as_mut().storage);
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    // the immediate, locked and vesting credits are all owed
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(2850u128), total);
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::WithdrawAll{}).unwrap();
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(2375u128), total);
+}
+
+#[test]
+fn migrate_rejects_downgrade() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Cannot migrate from a newer version"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    set_contract_version(deps.as_mut().storage, "crates.io:other", "0.0.1").unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Cannot migrate from a different contract"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_withdraw_fee_to_recipient() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: Some("treasury0000".to_string()), amount: None };
+
+    let withdraw_fee_info = mock_info("addr0000", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), withdraw_fee_info, withdraw_fee_msg).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "treasury0000".to_string(),
+                amount: Uint128::from(50u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })),
+        msg_transfer,
+    );
+}
+
+#[test]
+fn execute_update_fee_recipient() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let update_msg = ExecuteMsg::UpdateFeeRecipient{ recipient: "treasury0000".to_string() };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), update_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg).unwrap();
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!("treasury0000".to_string(), config.fee_recipient);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    // the owner withdraws, the stored recipient receives
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "treasury0000".to_string(),
+                amount: Uint128::from(50u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })),
+        msg_transfer,
+    );
+}
+
+#[test]
+fn execute_withdraw_fee_partial() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: Some(Uint128::from(20u128)) };
+
+    let withdraw_fee_info = mock_info("addr0000", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), withdraw_fee_info, withdraw_fee_msg).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(20u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })),
+        msg_transfer,
+    );
+
+    let fee: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(30u128), fee);
+}
+
+#[test]
+fn execute_withdraw_fee_exceeds_collected() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: Some(Uint128::from(51u128)) };
+
+    let withdraw_fee_info = mock_info("addr0000", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), withdraw_fee_info, withdraw_fee_msg).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Insufficient fee"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let fee: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee);
+}
+
+#[test]
+fn execute_deposit_overflow() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::MAX - Uint128::from(1u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap_err();
+    match res {
+        ContractError::Std(StdError::Overflow { .. }) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_msg_variants() {
+    // keep in sync with ExecuteMsg, the match fails to compile when a variant is missing
+    let msgs = vec![
+        ExecuteMsg::Withdraw { amount: Uint128::from(1u128), recipient: None, forward_contract: None, forward_msg: None },
+        ExecuteMsg::WithdrawAll {},
+        ExecuteMsg::WithdrawFee { recipient: None, amount: None },
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0000".to_string(),
+            msg: Binary::default(),
+            amount: Uint128::from(1u128),
+        }),
+        ExecuteMsg::TransferOwnership { new_owner: "addr0001".to_string() },
+        ExecuteMsg::AcceptOwnership {},
+        ExecuteMsg::DepositNative(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        }),
+        ExecuteMsg::WithdrawNative { amount: Uint128::from(1u128), recipient: None },
+        ExecuteMsg::WithdrawNativeFee { recipient: None },
+        ExecuteMsg::WithdrawToken {
+            token: "asset0002".to_string(),
+            amount: Uint128::from(1u128),
+            recipient: None,
+        },
+        ExecuteMsg::WithdrawTokenFee { token: "asset0002".to_string(), recipient: None },
+        ExecuteMsg::SweepUnallocated {},
+        ExecuteMsg::UpdateToken { token: "asset0002".to_string() },
+        ExecuteMsg::AddDepositor { addr: "addr0001".to_string() },
+        ExecuteMsg::RemoveDepositor { addr: "addr0001".to_string() },
+        ExecuteMsg::AddOperator { addr: "addr0001".to_string() },
+        ExecuteMsg::RemoveOperator { addr: "addr0001".to_string() },
+        ExecuteMsg::WithdrawFor { beneficiary: "addr0002".to_string(), amount: Uint128::from(1u128) },
+        ExecuteMsg::UpdateFee { fee_numerator: Some(10), fee_denominator: Some(1000), fee_bps: None },
+        ExecuteMsg::CreditBalance { recipient: "addr0002".to_string(), amount: Uint128::from(1u128) },
+        ExecuteMsg::UpdateShares { shareholders: vec![] },
+        ExecuteMsg::SetTokenConfig { token: "asset0002".to_string(), fee_numerator: Some(1), fee_denominator: Some(100), fee_bps: None, min_deposit: None, enabled: true },
+        ExecuteMsg::Revoke { recipient: "addr0002".to_string(), amount: Uint128::from(1u128) },
+        ExecuteMsg::ResetFeeAccounting {},
+        ExecuteMsg::WithdrawAndExecute { amount: Uint128::from(1u128), contract: "exchange0000".to_string(), msg: Binary::default() },
+        ExecuteMsg::UpdateMinDeposit { min_deposit: Uint128::from(1u128) },
+        ExecuteMsg::UpdateFeeRecipient { recipient: "addr0001".to_string() },
+        ExecuteMsg::DistributeBalance { recipients: vec![] },
+        ExecuteMsg::WithdrawPercent { bps: 5000 },
+        ExecuteMsg::FreezeAccount { addr: "addr0002".to_string() },
+        ExecuteMsg::UnfreezeAccount { addr: "addr0002".to_string() },
+    ];
+
+    for msg in msgs {
+        let name = match msg {
+            ExecuteMsg::Withdraw { .. } => "withdraw",
+            ExecuteMsg::WithdrawAll {} => "withdraw_all",
+            ExecuteMsg::WithdrawFee { .. } => "withdraw_fee",
+            ExecuteMsg::Receive(_) => "receive",
+            ExecuteMsg::TransferOwnership { .. } => "transfer_ownership",
+            ExecuteMsg::AcceptOwnership {} => "accept_ownership",
+            ExecuteMsg::DepositNative(_) => "deposit_native",
+            ExecuteMsg::WithdrawNative { .. } => "withdraw_native",
+            ExecuteMsg::WithdrawNativeFee { .. } => "withdraw_native_fee",
+            ExecuteMsg::WithdrawToken { .. } => "withdraw_token",
+            ExecuteMsg::WithdrawTokenFee { .. } => "withdraw_token_fee",
+            ExecuteMsg::SweepUnallocated {} => "sweep_unallocated",
+            ExecuteMsg::UpdateToken { .. } => "update_token",
+            ExecuteMsg::AddDepositor { .. } => "add_depositor",
+            ExecuteMsg::RemoveDepositor { .. } => "remove_depositor",
+            ExecuteMsg::AddOperator { .. } => "add_operator",
+            ExecuteMsg::RemoveOperator { .. } => "remove_operator",
+            ExecuteMsg::WithdrawFor { .. } => "withdraw_for",
+            ExecuteMsg::UpdateFee { .. } => "update_fee",
+            ExecuteMsg::CreditBalance { .. } => "credit_balance",
+            ExecuteMsg::UpdateShares { .. } => "update_shares",
+            ExecuteMsg::SetTokenConfig { .. } => "set_token_config",
+            ExecuteMsg::Revoke { .. } => "revoke",
+            ExecuteMsg::ResetFeeAccounting {} => "reset_fee_accounting",
+            ExecuteMsg::WithdrawAndExecute { .. } => "withdraw_and_execute",
+            ExecuteMsg::UpdateMinDeposit { .. } => "update_min_deposit",
+            ExecuteMsg::UpdateFeeRecipient { .. } => "update_fee_recipient",
+            ExecuteMsg::DistributeBalance { .. } => "distribute_balance",
+            ExecuteMsg::WithdrawPercent { .. } => "withdraw_percent",
+            ExecuteMsg::FreezeAccount { .. } => "freeze_account",
+            ExecuteMsg::UnfreezeAccount { .. } => "unfreeze_account",
+        };
+        let json = String::from_utf8(to_vec(&msg).unwrap()).unwrap();
+        assert!(json.starts_with(&format!("{{\"{}\":", name)));
+    }
+}
+
+#[test]
+fn execute_withdraw_to_recipient() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: Some("addr9999".to_string()), forward_contract: None, forward_msg: None };
+
+    let withdraw_info = mock_info("addr0002", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), withdraw_info, withdraw_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(175u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr9999".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::reply_always(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr9999".to_string(),
+                amount: Uint128::from(300u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }), 1),
+        msg_transfer,
+    );
+}
+
+#[test]
+fn execute_withdraw_all_nothing_to_withdraw() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let withdraw_info = mock_info("addr0002", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), withdraw_info, ExecuteMsg::WithdrawAll{}).unwrap_err();
+    match res {
+        ContractError::NothingToWithdraw {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_attributes() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1001u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "deposit"),
+            attr("amount", "1001"),
+            attr("fee", "50"),
+            attr("addr1", "addr0002"),
+            attr("amount1", "475"),
+            attr("addr2", "addr0003"),
+            attr("amount2", "476"),
+            attr("deposit_id", "1"),
+        ]
+    );
+}
+
+#[test]
+fn execute_withdraw_attributes() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+
+    let withdraw_info = mock_info("addr0002", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), withdraw_info, withdraw_msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "withdraw"),
+            attr("recipient", "addr0002"),
+            attr("amount", "300"),
+            attr("remaining", "175"),
+        ]
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("token_distribution")
+            .add_attribute("action", "withdraw")
+            .add_attribute("account", "addr0002")
+            .add_attribute("recipient", "addr0002")
+            .add_attribute("amount", "300")]
+    );
+}
+
+#[test]
+fn query_config() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_numerator: Some(25),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!(
+        ConfigResponse {
+            token: "asset0001".to_string(),
+            owner: "addr0000".to_string(),
+            fee_numerator: 25,
+            fee_denominator: 1000,
+            fee_bps: 250,
+            split_bps: 5000,
+            native_denom: None,
+            min_deposit: Uint128::zero(),
+            rounding: RoundingMode::RoundDown,
+            fee_mode: FeeMode::Accrue,
+            withdraw_cooldown: 0,
+            max_fee: None,
+            burn_fee: false,
+            max_total_withdrawable: None,
+            remainder_to: RemainderTo::Addr2,
+            recovery_addr: None,
+            decimals: 8,
+            symbol: "mAAPL".to_string(),
+            withdraw_fee_bps: 0,
+            forbid_owner_recipient: false,
+            deflationary_token: false,
+            check_contract_balance: false,
+            fee_recipient: "addr0000".to_string(),
+            max_recipients: 20,
+            group: None,
+            dust_policy: DustPolicy::LastRecipient,
+        },
+        config
+    );
+}
+
+#[test]
+fn query_total_earned() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info.clone(), deposit_msg.clone()).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let total_earned: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalEarned{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(950u128), total_earned);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(650u128), withdrawable);
+    let total_earned: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalEarned{ addr: "addr0004".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), total_earned);
+}
+
+#[test]
+fn execute_deposit_native() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        native_denom: Some("uatom".to_string()),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::DepositNative(DepositMsg {
+        addr1: Some("addr0002".to_string()),
+        addr2: Some("addr0003".to_string()),
+        unlock_time: None,
+        vesting: None,
+        min_recipient_amount: None,
+        deadline: None,
+        nonce: None,
+    });
+
+    // only the configured denom is accepted
+    let deposit_info = mock_info("addr0000", &coins(1000u128, "uluna"));
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg.clone()).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Invalid funds"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let deposit_info = mock_info("addr0000", &coins(1000u128, "uatom"));
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::NativeWithdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(475u128), withdrawable);
+
+    // native deposits do not credit the cw20 balances
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+
+    let withdraw_msg = ExecuteMsg::WithdrawNative{ amount: Uint128::from(300u128), recipient: None };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::NativeWithdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(175u128), withdrawable);
+
+    let msg_send = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0002".to_string(),
+            amount: coins(300u128, "uatom"),
+        })),
+        msg_send,
+    );
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::WithdrawNativeFee{ recipient: None }).unwrap();
+
+    let msg_send = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: coins(50u128, "uatom"),
+        })),
+        msg_send,
+    );
+}
+
+#[test]
+fn execute_deposit_native_disabled() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::DepositNative(DepositMsg {
+        addr1: Some("addr0002".to_string()),
+        addr2: Some("addr0003".to_string()),
+        unlock_time: None,
+        vesting: None,
+        min_recipient_amount: None,
+        deadline: None,
+        nonce: None,
+    });
+
+    let deposit_info = mock_info("addr0000", &coins(1000u128, "uatom"));
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Native deposits disabled"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_multiple_tokens() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(2000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0002", &[]), deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TokenWithdrawable{ token: "asset0001".to_string(), addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(475u128), withdrawable);
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TokenWithdrawable{ token: "asset0002".to_string(), addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(950u128), withdrawable);
+
+    let withdraw_msg = ExecuteMsg::WithdrawToken{
+        token: "asset0002".to_string(),
+        amount: Uint128::from(950u128),
+        recipient: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::reply_always(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0002".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0002".to_string(),
+                amount: Uint128::from(950u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }), 1),
+        msg_transfer,
+    );
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(475u128), recipient: None, forward_contract: None, forward_msg: None };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::reply_always(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0002".to_string(),
+                amount: Uint128::from(475u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }), 2),
+        msg_transfer,
+    );
+
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TokenWithdrawable{ token: "asset0002".to_string(), addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), withdrawable);
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TokenWithdrawable{ token: "asset0002".to_string(), addr: "addr0003".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(950u128), withdrawable);
+
+    // the configured token fee is untouched by deposits of other tokens
+    let fee: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::WithdrawTokenFee{ token: "asset0002".to_string(), recipient: None }).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0002".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(100u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })),
+        msg_transfer,
+    );
+}
+
+#[test]
+fn query_all_withdrawable() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{
+            recipients: vec![
+                "addr0002".to_string(),
+                "addr0003".to_string(),
+                "addr0004".to_string(),
+            ],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let page: Vec<(String, Uint128)> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::AllWithdrawable{ start_after: None, limit: Some(2) }).unwrap()).unwrap();
+    assert_eq!(
+        vec![
+            ("addr0002".to_string(), Uint128::from(316u128)),
+            ("addr0003".to_string(), Uint128::from(316u128)),
+        ],
+        page
+    );
+
+    let page: Vec<(String, Uint128)> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::AllWithdrawable{ start_after: Some("addr0003".to_string()), limit: Some(2) }).unwrap()).unwrap();
+    assert_eq!(vec![("addr0004".to_string(), Uint128::from(318u128))], page);
+}
+
+#[test]
+fn execute_deposit_min_deposit() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        min_deposit: Some(Uint128::from(100u128)),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(99u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info.clone(), deposit_msg).unwrap_err();
+    match res {
+        ContractError::DepositTooSmall {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(100u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(47u128), withdrawable);
+}
+
+#[test]
+fn execute_deposit_to_contract() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some(MOCK_CONTRACT_ADDR.to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap_err();
+    match res {
+        ContractError::InvalidRecipient { addr } => assert_eq!(MOCK_CONTRACT_ADDR, addr),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_batch_deposit() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposits = vec![
+        BatchDepositItem {
+            amount: Uint128::from(1000u128),
+            deposit: DepositMsg {
+                addr1: Some("addr0002".to_string()),
+                addr2: Some("addr0003".to_string()),
+                unlock_time: None,
+                vesting: None,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            },
+        },
+        BatchDepositItem {
+            amount: Uint128::from(200u128),
+            deposit: DepositMsg {
+                addr1: Some("addr0004".to_string()),
+                addr2: Some("addr0005".to_string()),
+                unlock_time: None,
+                vesting: None,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            },
+        },
+    ];
+
+    let deposit_info = mock_info("asset0001", &[]);
+
+    // the entries must add up to the received amount
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::BatchDeposit{ deposits: deposits.clone() }).unwrap(),
+        amount: Uint128::from(1100u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), deposit_info.clone(), deposit_msg).unwrap_err();
+    match res {
+        ContractError::BatchAmountMismatch { expected, actual } => {
+            assert_eq!(Uint128::from(1100u128), expected);
+            assert_eq!(Uint128::from(1200u128), actual);
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::BatchDeposit{ deposits }).unwrap(),
+        amount: Uint128::from(1200u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0005".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(95u128), withdrawable);
+
+    let fee: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(60u128), fee);
+}
+
+#[test]
+fn execute_batch_deposit_same_recipient() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // addr0002 is credited by both entries
+    let deposits = vec![
+        BatchDepositItem {
+            amount: Uint128::from(1000u128),
+            deposit: DepositMsg {
+                addr1: Some("addr0002".to_string()),
+                addr2: Some("addr0003".to_string()),
+                unlock_time: None,
+                vesting: None,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            },
+        },
+        BatchDepositItem {
+            amount: Uint128::from(200u128),
+            deposit: DepositMsg {
+                addr1: Some("addr0004".to_string()),
+                addr2: Some("addr0002".to_string()),
+                unlock_time: None,
+                vesting: None,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            },
+        },
+    ];
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::BatchDeposit{ deposits }).unwrap(),
+        amount: Uint128::from(1200u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(570u128), withdrawable);
+    let total_earned: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalEarned{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(570u128), total_earned);
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1140u128), total);
+
+    // the history keeps a single entry for the deposit
+    let history: Vec<(u64, Uint128)> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::DepositsByRecipient{ addr: "addr0002".to_string(), start_after: None, limit: None }).unwrap()).unwrap();
+    assert_eq!(vec![(1, Uint128::from(570u128))], history);
+}
+
+#[test]
+fn sudo_set_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    sudo(deps.as_mut(), mock_env(), SudoMsg::SetFee { fee_numerator: Some(10), fee_denominator: Some(1000), fee_bps: None }).unwrap();
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!((10, 1000), (config.fee_numerator, config.fee_denominator));
+
+    let res = sudo(deps.as_mut(), mock_env(), SudoMsg::SetFee { fee_numerator: Some(1001), fee_denominator: Some(1000), fee_bps: None }).unwrap_err();
+    match res {
+        ContractError::InvalidFee {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn reply_restores_failed_withdraw() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg.clone()).unwrap();
+
+    let res = reply(deps.as_mut(), mock_env(), Reply {
+        id: 1,
+        result: SubMsgResult::Err("transfer failed".to_string()),
+    }).unwrap();
+    assert_eq!(res.attributes[0], attr("action", "withdraw_failed"));
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+
+    // a successful transfer keeps the debit
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    reply(deps.as_mut(), mock_env(), Reply {
+        id: 2,
+        result: SubMsgResult::Ok(SubMsgResponse { events: vec![], data: None }),
+    }).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(175u128), withdrawable);
+
+    // each reply is only handled once
+    reply(deps.as_mut(), mock_env(), Reply {
+        id: 2,
+        result: SubMsgResult::Err("transfer failed".to_string()),
+    }).unwrap_err();
+}
+
+#[test]
+fn execute_sweep_unallocated() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    // 1000 deposited plus 250 sent directly to the contract
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1250u128)),
+        ],
+    )]);
+
+    let sweep_msg = ExecuteMsg::SweepUnallocated {};
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), sweep_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), sweep_msg.clone()).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(250u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })),
+        msg_transfer,
+    );
+
+    // once swept, the balance matches the accounting
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1000u128)),
+        ],
+    )]);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), sweep_msg);
+    match res {
+        Err(ContractError::NothingToWithdraw {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_rounding() {
+    for (rounding, fee) in [(None, 0u128), (Some(RoundingMode::RoundUp), 1u128)] {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {
+            rounding,
+            ..default_instantiate_msg()
+        };
+
+        instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+        let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0000".to_string(),
+            msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+                addr1: Some("addr0002".to_string()),
+                addr2: Some("addr0003".to_string()),
+                unlock_time: None,
+                vesting: None,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            })).unwrap(),
+            amount: Uint128::from(19u128),
+        });
+
+        execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+        let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+        assert_eq!(Uint128::from(fee), fee_collected);
+    }
+
+    // rounding up never takes more than the deposit
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_numerator: Some(1000),
+        rounding: Some(RoundingMode::RoundUp),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1u128), fee_collected);
+}
+
+#[test]
+fn execute_deposit_fee_mode_forward() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_mode: Some(FeeMode::Forward { recipient: "addr0009".to_string() }),
+        withdraw_fee_bps: Some(100),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0009".to_string(),
+                amount: Uint128::from(50u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })),
+        msg_transfer,
+    );
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), fee_collected);
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+
+    // there is nothing to withdraw
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg.clone()).unwrap_err();
+    match res {
+        ContractError::FeeModeIsForward {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the withdraw fee still accrues
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(400u128), recipient: None, forward_contract: None, forward_msg: None };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(4u128), fee_collected);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(4u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })),
+        msg_transfer,
+    );
+}
+
+#[test]
+fn execute_deposit_native_fee_mode_forward() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        native_denom: Some("uatom".to_string()),
+        fee_mode: Some(FeeMode::Forward { recipient: "addr0009".to_string() }),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::DepositNative(DepositMsg {
+        addr1: Some("addr0002".to_string()),
+        addr2: Some("addr0003".to_string()),
+        unlock_time: None,
+        vesting: None,
+        min_recipient_amount: None,
+        deadline: None,
+        nonce: None,
+    });
+
+    let deposit_info = mock_info("addr0000", &coins(1000u128, "uatom"));
+    let res = execute(deps.as_mut(), mock_env(), deposit_info, deposit_msg).unwrap();
+
+    let msg_send = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0009".to_string(),
+            amount: coins(50u128, "uatom"),
+        })),
+        msg_send,
+    );
+
+    let withdrawable: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::NativeWithdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(475u128), withdrawable);
+
+    // the forwarded fee is not accrued
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::WithdrawNativeFee{ recipient: None }).unwrap_err();
+    match res {
+        ContractError::ZeroAmount {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_fee_mode_accrue() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_mode: Some(FeeMode::Accrue),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee_collected);
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+}
+
+#[test]
+fn execute_update_token() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    deps.querier.with_token_balances(&[(&"asset0002".to_string(), &[])]);
+
+    let update_msg = ExecuteMsg::UpdateToken { token: "asset0002".to_string() };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), update_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // blocked while fees and balances are outstanding
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg.clone());
+    match res {
+        Err(ContractError::OutstandingBalances {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::WithdrawFee { recipient: None, amount: None }).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::WithdrawAll {}).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg.clone());
+    match res {
+        Err(ContractError::OutstandingBalances {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0003", &[]), ExecuteMsg::WithdrawAll {}).unwrap();
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg).unwrap();
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!("asset0002".to_string(), config.token);
+}
+
+#[test]
+fn execute_deposit_allowlist() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = |sender: &str| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: sender.to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let add_msg = ExecuteMsg::AddDepositor { addr: "addr0001".to_string() };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), add_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), add_msg).unwrap();
+
+    // allowed depositor
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg("addr0001")).unwrap();
+
+    // blocked depositor
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg("addr0004"));
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // anyone can deposit again once the list is empty
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::RemoveDepositor { addr: "addr0001".to_string() }).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg("addr0004")).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(950u128), withdrawable);
+}
+
+#[test]
+fn execute_deposit_unlock_time() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let unlock_time = mock_env().block.time.plus_seconds(3600);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: Some(unlock_time),
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg.clone());
+    match res {
+        Err(ContractError::InsufficientFunds { requested, available }) => {
+            assert_eq!(Uint128::from(300u128), requested);
+            assert_eq!(Uint128::zero(), available);
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0003", &[]), ExecuteMsg::WithdrawAll {});
+    match res {
+        Err(ContractError::NothingToWithdraw {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let mut env = mock_env();
+    env.block.time = unlock_time;
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), env.clone(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+
+    execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), env.clone(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(175u128), withdrawable);
+
+    execute(deps.as_mut(), env, mock_info("addr0003", &[]), ExecuteMsg::WithdrawAll {}).unwrap();
+}
+
+#[test]
+fn execute_deposit_vesting() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let start = mock_env().block.time;
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: Some(VestingSchedule { start, end: start.plus_seconds(1000) }),
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    // 0%
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::WithdrawAll {});
+    match res {
+        Err(ContractError::NothingToWithdraw {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // 50%
+    let mut env = mock_env();
+    env.block.time = start.plus_seconds(500);
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), env.clone(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(237u128), withdrawable);
+
+    execute(deps.as_mut(), env, mock_info("addr0002", &[]), ExecuteMsg::WithdrawAll {}).unwrap();
+
+    // 100%, what was already withdrawn is not released again
+    let mut env = mock_env();
+    env.block.time = start.plus_seconds(2000);
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), env.clone(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(238u128), withdrawable);
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(238u128), recipient: None, forward_contract: None, forward_msg: None };
+    execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), env, QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+}
+
+#[test]
+fn query_token_balance() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1234u128)),
+            (&"addr0001".to_string(), &Uint128::from(1000000u128)),
+        ],
+    )]);
+
+    let balance: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TokenBalance{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1234u128), balance);
+}
+
+#[test]
+fn query_total_withdrawable() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1900u128), total);
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("addr0003", &[]), ExecuteMsg::WithdrawAll {}).unwrap();
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(650u128), total);
+}
+
+#[test]
+fn execute_withdraw_insufficient_funds() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(500u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap_err();
+    // clients parse the amounts out of the message
+    assert_eq!("Insufficient funds: requested 500, available 475", res.to_string());
+    match res {
+        ContractError::InsufficientFunds { requested, available } => {
+            assert_eq!(Uint128::from(500u128), requested);
+            assert_eq!(Uint128::from(475u128), available);
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::zero(), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap_err();
+    match res {
+        ContractError::ZeroAmount {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_withdraw_for() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::WithdrawFor { beneficiary: "addr0002".to_string(), amount: Uint128::from(300u128) };
+
+    // unauthorized caller
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), withdraw_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::AddOperator { addr: "addr0001".to_string() }).unwrap();
+
+    // authorized operator, the beneficiary receives the tokens
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), withdraw_msg).unwrap();
+
+    let msg_transfer = res.messages.first().expect("no message");
+    assert_eq!(
+        &SubMsg::reply_always(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0002".to_string(),
+                amount: Uint128::from(300u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }), 1),
+        msg_transfer,
+    );
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(175u128), withdrawable);
+}
+
+#[test]
+fn execute_withdraw_cooldown() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        withdraw_cooldown: Some(3600),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(100u128), recipient: None, forward_contract: None, forward_msg: None };
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg.clone()).unwrap();
+
+    // blocked within the cooldown
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3599);
+
+    let res = execute(deps.as_mut(), env, mock_info("addr0002", &[]), withdraw_msg.clone());
+    match res {
+        Err(ContractError::CooldownActive {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // other recipients are not affected
+    execute(deps.as_mut(), mock_env(), mock_info("addr0003", &[]), withdraw_msg.clone()).unwrap();
+
+    // allowed once the cooldown has passed
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3600);
+
+    execute(deps.as_mut(), env, mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(275u128), withdrawable);
+}
+
+#[test]
+fn reply_restores_withdraw_cooldown() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        withdraw_cooldown: Some(3600),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(100u128), recipient: None, forward_contract: None, forward_msg: None };
+
+    // the first withdrawal fails, the retry is not held by the cooldown
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg.clone()).unwrap();
+    reply(deps.as_mut(), mock_env(), Reply {
+        id: 1,
+        result: SubMsgResult::Err("transfer failed".to_string()),
+    }).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(60);
+
+    execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), withdraw_msg.clone()).unwrap();
+    reply(deps.as_mut(), env.clone(), Reply {
+        id: 2,
+        result: SubMsgResult::Ok(SubMsgResponse { events: vec![], data: None }),
+    }).unwrap();
+
+    // a failure once the cooldown has passed restores the time of the successful withdrawal
+    env.block.time = env.block.time.plus_seconds(3600);
+
+    execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), withdraw_msg.clone()).unwrap();
+    reply(deps.as_mut(), env.clone(), Reply {
+        id: 3,
+        result: SubMsgResult::Err("transfer failed".to_string()),
+    }).unwrap();
+
+    execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), withdraw_msg.clone()).unwrap();
+
+    let res = execute(deps.as_mut(), env, mock_info("addr0002", &[]), withdraw_msg);
+    match res {
+        Err(ContractError::CooldownActive {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_max_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        max_fee: Some(Uint128::from(1000u128)),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = |amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(amount),
+    });
+
+    // the cap does not bind, 5% of 10000
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg(10000)).unwrap();
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(500u128), fee_collected);
+
+    // the cap binds, 5% of 1000000 would be 50000
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg(1000000)).unwrap();
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1500u128), fee_collected);
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(4750u128 + 499500u128), withdrawable);
+}
+
+#[test]
+fn query_preview_deposit() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        split_bps: 7000,
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let preview: PreviewResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PreviewDeposit{ amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    let amount1: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    let amount2: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(PreviewResponse { fee: fee_collected, amount1, amount2 }, preview);
+    assert_eq!(
+        PreviewResponse {
+            fee: Uint128::from(50u128),
+            amount1: Uint128::from(665u128),
+            amount2: Uint128::from(285u128),
+        },
+        preview
+    );
+}
+
+#[test]
+fn compute_split_rounding() {
+    let split = |amount: u128, rounding| {
+        let (fee, amount1, amount2) = compute_split(Uint128::from(amount), 50, 1000, 5000, rounding, None, RemainderTo::Addr2, DustPolicy::LastRecipient).unwrap();
+        (fee.u128(), amount1.u128(), amount2.u128())
+    };
+
+    assert_eq!((50, 475, 475), split(1000, RoundingMode::RoundDown));
+    // odd amounts, the truncated half goes to addr2
+    assert_eq!((50, 475, 476), split(1001, RoundingMode::RoundDown));
+    assert_eq!((51, 475, 475), split(1001, RoundingMode::RoundUp));
+    // amount of 1
+    assert_eq!((0, 0, 1), split(1, RoundingMode::RoundDown));
+    assert_eq!((1, 0, 0), split(1, RoundingMode::RoundUp));
+    // large values
+    assert_eq!(
+        (5 * 10u128.pow(33), 475 * 10u128.pow(32), 475 * 10u128.pow(32)),
+        split(10u128.pow(35), RoundingMode::RoundDown)
+    );
+    compute_split(Uint128::MAX, 50, 1000, 5000, RoundingMode::RoundDown, None, RemainderTo::Addr2, DustPolicy::LastRecipient).unwrap_err();
+
+    // the cap only lowers the fee
+    let (fee, amount1, amount2) = compute_split(Uint128::from(1000u128), 50, 1000, 5000, RoundingMode::RoundDown, Some(Uint128::from(10u128)), RemainderTo::Addr2, DustPolicy::LastRecipient).unwrap();
+    assert_eq!((Uint128::from(10u128), Uint128::from(495u128), Uint128::from(495u128)), (fee, amount1, amount2));
+}
+
+#[test]
+fn execute_update_min_deposit() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let update_msg = ExecuteMsg::UpdateMinDeposit { min_deposit: Uint128::from(1000u128) };
+
+    // non-owner
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), update_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg).unwrap();
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1000u128), config.min_deposit);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(999u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::DepositTooSmall {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_update_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+
+    // non-owner
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), ExecuteMsg::UpdateFee { fee_numerator: Some(100), fee_denominator: Some(1000), fee_bps: None });
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // out of range
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::UpdateFee { fee_numerator: Some(1), fee_denominator: Some(0), fee_bps: None });
+    match res {
+        Err(ContractError::InvalidFee {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::UpdateFee { fee_numerator: Some(100), fee_denominator: Some(1000), fee_bps: None }).unwrap();
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!((100, 1000), (config.fee_numerator, config.fee_denominator));
+
+    // the earlier deposit keeps its amounts, the next one uses the new fee
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128 + 450u128), withdrawable);
+}
+
+#[test]
+fn execute_withdraw_fee_attributes() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: Some("addr0005".to_string()), amount: Some(Uint128::from(20u128)) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "withdraw_fee"),
+            attr("recipient", "addr0005"),
+            attr("amount", "20"),
+            attr("remaining", "30"),
+        ]
+    );
+
+    // a full sweep leaves nothing
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "withdraw_fee"),
+            attr("recipient", "addr0000"),
+            attr("amount", "30"),
+            attr("remaining", "0"),
+        ]
+    );
+}
+
+#[test]
+fn execute_withdraw_all_single_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::WithdrawAll {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0002".to_string(),
+                amount: Uint128::from(475u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }), 1)],
+    );
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(475u128), total);
+}
+
+#[test]
+fn query_deposit() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = |amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(amount),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg(1000)).unwrap();
+    assert_eq!(res.attributes.last(), Some(&attr("deposit_id", "1")));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg(2000)).unwrap();
+    assert_eq!(res.attributes.last(), Some(&attr("deposit_id", "2")));
+
+    let record: DepositRecord = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Deposit{ id: 2 }).unwrap()).unwrap();
+    assert_eq!(
+        DepositRecord {
+            sender: Addr::unchecked("addr0001"),
+            token: Addr::unchecked("asset0001"),
+            amount: Uint128::from(2000u128),
+            fee: Uint128::from(100u128),
+            time: mock_env().block.time,
+        },
+        record
+    );
+
+    query(deps.as_ref(), mock_env(), QueryMsg::Deposit{ id: 3 }).unwrap_err();
+}
+
+#[test]
+fn query_stats() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let stats: StatsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Stats{}).unwrap()).unwrap();
+    assert_eq!(
+        StatsResponse {
+            total_deposited: Uint128::zero(),
+            total_withdrawn: Uint128::zero(),
+            total_fees_collected: Uint128::zero(),
+            deposit_count: 0,
+        },
+        stats
+    );
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+
+    let stats: StatsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Stats{}).unwrap()).unwrap();
+    assert_eq!(
+        StatsResponse {
+            total_deposited: Uint128::from(1000u128),
+            total_withdrawn: Uint128::from(300u128),
+            total_fees_collected: Uint128::from(50u128),
+            deposit_count: 1,
+        },
+        stats
+    );
+
+    // a failed transfer is not counted
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Err("transfer failed".to_string()),
+    };
+    reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let stats: StatsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Stats{}).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), stats.total_withdrawn);
+}
+
+#[test]
+fn query_deposits_by_recipient() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    for (addr1, addr2) in [("addr0002", "addr0003"), ("addr0004", "addr0005")] {
+        let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0000".to_string(),
+            msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+                addr1: Some(addr1.to_string()),
+                addr2: Some(addr2.to_string()),
+                unlock_time: None,
+                vesting: None,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            })).unwrap(),
+            amount: Uint128::from(1000u128),
+        });
+        execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    }
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{
+            recipients: vec!["addr0002".to_string(), "addr0004".to_string()],
+        }).unwrap(),
+        amount: Uint128::from(2000u128),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let history: Vec<(u64, Uint128)> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::DepositsByRecipient{ addr: "addr0002".to_string(), start_after: None, limit: None }).unwrap()).unwrap();
+    assert_eq!(vec![(1, Uint128::from(475u128)), (3, Uint128::from(950u128))], history);
+
+    let history: Vec<(u64, Uint128)> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::DepositsByRecipient{ addr: "addr0002".to_string(), start_after: Some(1), limit: None }).unwrap()).unwrap();
+    assert_eq!(vec![(3, Uint128::from(950u128))], history);
+
+    let history: Vec<(u64, Uint128)> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::DepositsByRecipient{ addr: "addr0003".to_string(), start_after: None, limit: None }).unwrap()).unwrap();
+    assert_eq!(vec![(1, Uint128::from(475u128))], history);
+}
+
+#[test]
+fn execute_receive_invalid_hook() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: Binary::from(b"not a hook".to_vec()),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::InvalidHookMsg {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn query_msg_owner_serialization() {
+    let json = String::from_utf8(to_vec(&QueryMsg::Owner {}).unwrap()).unwrap();
+    assert_eq!("{\"owner\":{}}", json);
+
+    let msg: QueryMsg = from_binary(&Binary::from(json.as_bytes())).unwrap();
+    assert_eq!(QueryMsg::Owner {}, msg);
+}
+
+#[test]
+fn query_is_owner() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let is_owner: bool = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::IsOwner{ addr: "addr0000".to_string() }).unwrap()).unwrap();
+    assert!(is_owner);
+    let is_owner: bool = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::IsOwner{ addr: "addr0001".to_string() }).unwrap()).unwrap();
+    assert!(!is_owner);
+
+    // the address is validated first
+    query(deps.as_ref(), mock_env(), QueryMsg::IsOwner{ addr: "".to_string() }).unwrap_err();
+}
+
+#[test]
+fn execute_deprecated_fee_bps() {
+    let mut deps = mock_dependencies(&[]);
+
+    // messages written against the bps fee keep working
+    let msg: InstantiateMsg = from_slice(br#"{"token":"asset0001","owner":"addr0000","fee_bps":250,"split_bps":5000}"#).unwrap();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_eq!((250, 10000, 250), (config.fee_numerator, config.fee_denominator, config.fee_bps));
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(25u128), fee_collected);
+
+    let update_msg: ExecuteMsg = from_slice(br#"{"update_fee":{"fee_bps":100}}"#).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg).unwrap();
+
+    let fee_info: FeeInfoResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeInfo{}).unwrap()).unwrap();
+    assert_eq!((100, 10000, 100), (fee_info.fee_numerator, fee_info.fee_denominator, fee_info.fee_bps));
+
+    // either the bps or the ratio, not both
+    let update_msg = ExecuteMsg::UpdateFee{ fee_numerator: Some(1), fee_denominator: Some(100), fee_bps: Some(100) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg);
+    match res {
+        Err(ContractError::InvalidFee {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_ppm_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_numerator: Some(2500),
+        fee_denominator: Some(1000000),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    // 0.25%
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(2500u128), fee_collected);
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(498750u128), withdrawable);
+
+    // the numerator cannot exceed the denominator
+    let msg = InstantiateMsg {
+        fee_numerator: Some(1000001),
+        fee_denominator: Some(1000000),
+        ..default_instantiate_msg()
+    };
+
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap_err();
+    match res {
+        ContractError::InvalidFee {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_withdraw_fee_burn() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        burn_fee: Some(true),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0001".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(50u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            0,
+        )]
+    );
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), fee_collected);
+
+    // a token without Burn fails the whole withdrawal
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 0,
+            result: SubMsgResult::Err("Unknown variant burn".to_string()),
+        },
+    )
+    .unwrap_err();
+    match res {
+        ContractError::BurnFailed { .. } => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn query_depositors_and_operators() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    for addr in ["addr0002", "addr0003", "addr0004"] {
+        let add_msg = ExecuteMsg::AddDepositor{ addr: addr.to_string() };
+        execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), add_msg).unwrap();
+    }
+    let remove_msg = ExecuteMsg::RemoveDepositor{ addr: "addr0003".to_string() };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), remove_msg).unwrap();
+
+    let add_msg = ExecuteMsg::AddOperator{ addr: "addr0005".to_string() };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), add_msg).unwrap();
+
+    let page: Vec<String> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Depositors{ start_after: None, limit: Some(1) }).unwrap()).unwrap();
+    assert_eq!(vec!["addr0002".to_string()], page);
+
+    let page: Vec<String> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Depositors{ start_after: Some("addr0002".to_string()), limit: None }).unwrap()).unwrap();
+    assert_eq!(vec!["addr0004".to_string()], page);
+
+    let page: Vec<String> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Operators{ start_after: None, limit: None }).unwrap()).unwrap();
+    assert_eq!(vec!["addr0005".to_string()], page);
+}
+
+#[test]
+fn execute_deposit_and_withdraw_paths() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // every handler receives the env of the block it runs in
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(1000);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    execute(deps.as_mut(), env.clone(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(100u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+
+    let withdraw_all_msg = ExecuteMsg::WithdrawAll{};
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0003", &[]), withdraw_all_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), env.clone(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(375u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), env, QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+}
+
+#[test]
+fn execute_credit_balance() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 1000 sent directly to the contract
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1000u128)),
+        ],
+    )]);
+
+    let credit_msg = ExecuteMsg::CreditBalance{ recipient: "addr0002".to_string(), amount: Uint128::from(600u128) };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), credit_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), credit_msg.clone()).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(600u128), withdrawable);
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(600u128), total);
+
+    // only 400 are left unallocated
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), credit_msg).unwrap_err();
+    match res {
+        ContractError::InsufficientFunds { requested, available } => {
+            assert_eq!(Uint128::from(600u128), requested);
+            assert_eq!(Uint128::from(400u128), available);
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_distribute_balance() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    // the deposit plus 301 sent directly to the contract
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1301u128)),
+        ],
+    )]);
+
+    let distribute_msg = ExecuteMsg::DistributeBalance{ recipients: vec!["addr0004".to_string(), "addr0005".to_string()] };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), distribute_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), distribute_msg.clone()).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0004".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(150u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0005".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(151u128), withdrawable);
+
+    // the earlier deposit and its fee are untouched
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee_collected);
+
+    // nothing is left to distribute
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), distribute_msg).unwrap_err();
+    match res {
+        ContractError::NothingToWithdraw {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_cap() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        max_total_withdrawable: Some(Uint128::from(1000u128)),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 950 credited after the fee
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    // 50 more reach the cap exactly
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(52u128),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1000u128), total);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(10u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::CapExceeded {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // 48 held beyond what the contract owes, the owner cannot credit them past the cap either
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1100u128))],
+    )]);
+
+    let credit_msg = ExecuteMsg::CreditBalance{ recipient: "addr0004".to_string(), amount: Uint128::from(10u128) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), credit_msg).unwrap_err();
+    match res {
+        ContractError::CapExceeded {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let distribute_msg = ExecuteMsg::DistributeBalance{ recipients: vec!["addr0004".to_string()] };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), distribute_msg).unwrap_err();
+    match res {
+        ContractError::CapExceeded {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_withdraw_forward() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw{
+        amount: Uint128::from(300u128),
+        recipient: None,
+        forward_contract: Some("exchange0000".to_string()),
+        forward_msg: Some(Binary::from(b"memo".to_vec())),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0001".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "exchange0000".to_string(),
+                    amount: Uint128::from(300u128),
+                    msg: Binary::from(b"memo".to_vec()),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            1,
+        )]
+    );
+
+    let withdraw_msg = ExecuteMsg::WithdrawAndExecute{
+        amount: Uint128::from(100u128),
+        contract: "exchange0000".to_string(),
+        msg: Binary::from(b"swap".to_vec()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0001".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "exchange0000".to_string(),
+                    amount: Uint128::from(100u128),
+                    msg: Binary::from(b"swap".to_vec()),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            2,
+        )]
+    );
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(75u128), withdrawable);
+
+    // a hook needs a contract to be forwarded to
+    let withdraw_msg = ExecuteMsg::Withdraw{
+        amount: Uint128::from(100u128),
+        recipient: None,
+        forward_contract: None,
+        forward_msg: Some(Binary::from(b"memo".to_vec())),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Invalid forward"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_state_saved_when_messages_returned() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_mode: Some(FeeMode::Forward { recipient: "addr0000".to_string() }),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    // the forwarded fee is already recorded when its transfer is returned
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    let record: DepositRecord = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Deposit{ id: 1 }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), record.fee);
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(950u128), total);
+
+    // balances are debited when the transfers are returned
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(175u128), withdrawable);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0003", &[]), ExecuteMsg::WithdrawAll{}).unwrap();
+    assert_eq!(1, res.messages.len());
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::zero(), withdrawable);
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(175u128), total);
+
+    // revoke returns no message, the revoked amount is already in the fees
+    let revoke_msg = ExecuteMsg::Revoke{ recipient: "addr0002".to_string(), amount: Uint128::from(75u128) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), revoke_msg).unwrap();
+    assert_eq!(0, res.messages.len());
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(100u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(75u128), fee_collected);
+
+    let withdraw_for_msg = ExecuteMsg::WithdrawFor{ beneficiary: "addr0002".to_string(), amount: Uint128::from(50u128) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_for_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(50u128), withdrawable);
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), total);
+
+    // the collected fees are zeroed when their transfer is returned
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), fee_collected);
+}
+
+#[test]
+fn execute_deposit_shares() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        shareholders: Some(vec![
+            ("addr0002".to_string(), 60),
+            ("addr0003".to_string(), 40),
+        ]),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: None,
+            addr2: None,
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(570u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(380u128), withdrawable);
+
+    let update_msg = ExecuteMsg::UpdateShares{ shareholders: vec![("addr0004".to_string(), 1)] };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), update_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg).unwrap();
+
+    // the previous shareholders are replaced
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(570u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0004".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(950u128), withdrawable);
+}
+
+#[test]
+fn execute_deposit_shares_invalid() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // no recipients and no shares configured
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: None,
+            addr2: None,
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg);
+    match res {
+        Err(ContractError::NoRecipients {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // a shareholder listed twice
+    let update_msg = ExecuteMsg::UpdateShares{ shareholders: vec![
+        ("addr0002".to_string(), 60),
+        ("addr0003".to_string(), 40),
+        ("addr0002".to_string(), 10),
+    ] };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg);
+    match res {
+        Err(ContractError::DuplicateShareholder { addr }) => assert_eq!("addr0002", addr),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = InstantiateMsg {
+        shareholders: Some(vec![
+            ("addr0002".to_string(), 60),
+            ("addr0002".to_string(), 40),
+        ]),
+        ..default_instantiate_msg()
+    };
+
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::DuplicateShareholder { addr }) => assert_eq!("addr0002", addr),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn query_share_of() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        shareholders: Some(vec![
+            ("addr0002".to_string(), 60),
+            ("addr0003".to_string(), 40),
+        ]),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let share: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ShareOf{ addr: "addr0002".to_string(), amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(570u128), share);
+    let share: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ShareOf{ addr: "addr0003".to_string(), amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(380u128), share);
+    let share: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ShareOf{ addr: "addr0004".to_string(), amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), share);
+
+    // a configured group takes precedence over the shares, as for a deposit
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_group_members("group0000", &[("addr0002", 1), ("addr0004", 3)]);
+
+    let msg = InstantiateMsg {
+        group: Some("group0000".to_string()),
+        shareholders: Some(vec![
+            ("addr0002".to_string(), 60),
+            ("addr0003".to_string(), 40),
+        ]),
+        ..default_instantiate_msg()
+    };
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let share: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ShareOf{ addr: "addr0002".to_string(), amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(237u128), share);
+    let share: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ShareOf{ addr: "addr0003".to_string(), amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), share);
+    let share: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ShareOf{ addr: "addr0004".to_string(), amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(713u128), share);
+}
+
+#[test]
+fn execute_deposit_token_configs() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier.with_token_balances(&[
+        (&"asset0001".to_string(), &[]),
+        (&"asset0002".to_string(), &[]),
+        (&"asset0003".to_string(), &[]),
+    ]);
+
+    let config_msg = ExecuteMsg::SetTokenConfig {
+        token: "asset0002".to_string(),
+        fee_numerator: Some(1),
+        fee_denominator: Some(100),
+        fee_bps: None,
+        min_deposit: None,
+        enabled: true,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), config_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), config_msg).unwrap();
+
+    let config_msg = ExecuteMsg::SetTokenConfig {
+        token: "asset0003".to_string(),
+        fee_numerator: Some(10),
+        fee_denominator: Some(100),
+        fee_bps: None,
+        min_deposit: Some(Uint128::from(100u128)),
+        enabled: true,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), config_msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    // 1% of asset0002, 10% of asset0003
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0002", &[]), deposit_msg.clone()).unwrap();
+    assert!(res.attributes.contains(&attr("fee", "10")));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0003", &[]), deposit_msg.clone()).unwrap();
+    assert!(res.attributes.contains(&attr("fee", "100")));
+
+    // the configured token keeps the fee of the contract
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+    assert!(res.attributes.contains(&attr("fee", "50")));
+
+    // unknown tokens are rejected once tokens are configured
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0004", &[]), deposit_msg.clone()).unwrap_err();
+    match res {
+        ContractError::TokenNotAccepted { addr } => assert_eq!("asset0004", addr),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let config_msg = ExecuteMsg::SetTokenConfig {
+        token: "asset0002".to_string(),
+        fee_numerator: Some(1),
+        fee_denominator: Some(100),
+        fee_bps: None,
+        min_deposit: None,
+        enabled: false,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), config_msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0002", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::TokenNotAccepted { addr } => assert_eq!("asset0002", addr),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(99u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0003", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::DepositTooSmall {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_remainder_to() {
+    for (remainder_to, amount1, amount2) in [(RemainderTo::Addr2, 47u128, 48u128), (RemainderTo::Addr1, 48u128, 47u128)] {
+        let mut deps = mock_dependencies(&[]);
+
+        // the fee of 101 is rounded up to 6, leaving 95 to split
+        let msg = InstantiateMsg {
+            rounding: Some(RoundingMode::RoundUp),
+            remainder_to: Some(remainder_to),
+            ..default_instantiate_msg()
+        };
+
+        let info = mock_info("addr0000", &[]);
+
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0000".to_string(),
+            msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+                addr1: Some("addr0002".to_string()),
+                addr2: Some("addr0003".to_string()),
+                unlock_time: None,
+                vesting: None,
+                min_recipient_amount: None,
+                deadline: None,
+                nonce: None,
+            })).unwrap(),
+            amount: Uint128::from(101u128),
+        });
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+        assert!(res.attributes.contains(&attr("fee", "6")));
+        assert!(res.attributes.contains(&attr("amount1", amount1.to_string())));
+        assert!(res.attributes.contains(&attr("amount2", amount2.to_string())));
+    }
+}
+
+// Checks that the keys of the serialized value are the properties of the schema of its type, of
+// the schema of its variant for an enum, and that the value reads back the same
+fn assert_matches_schema<T>(value: T)
+where
+    T: schemars::JsonSchema + serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let schema = serde_json::to_value(schemars::schema_for!(T)).unwrap();
+    let json = serde_json::to_value(&value).unwrap();
+
+    let (object_schema, object) = match schema["oneOf"].as_array() {
+        Some(variants) => {
+            let (name, inner) = json.as_object().unwrap().iter().next().unwrap();
+            let variant = variants
+                .iter()
+                .find(|variant| variant["required"] == serde_json::json!([name]))
+                .unwrap_or_else(|| panic!("no variant {} in the schema", name));
+            // tuple variants refer to the schema of their content
+            let variant_schema = &variant["properties"][name];
+            let variant_schema = match variant_schema["$ref"].as_str() {
+                Some(reference) => &schema["definitions"][reference.trim_start_matches("#/definitions/")],
+                None => variant_schema,
+            };
+            (variant_schema.clone(), inner.clone())
+        }
+        None => (schema, json.clone()),
+    };
+
+    let properties: Vec<String> = object_schema["properties"].as_object().map(|properties| properties.keys().cloned().collect()).unwrap_or_default();
+    let keys: Vec<String> = object.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(properties, keys);
+    for required in object_schema["required"].as_array().into_iter().flatten() {
+        assert!(keys.contains(&required.as_str().unwrap().to_string()));
+    }
+
+    let value_back: T = serde_json::from_value(json).unwrap();
+    assert_eq!(value, value_back);
+}
+
+#[test]
+fn schema_matches_messages() {
+    // one value of every type with a schema in examples/schema.rs, the responses as the queries
+    // return them
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+    assert_matches_schema(msg.clone());
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let hook_msg = Cw20HookMsg::Deposit(DepositMsg {
+        addr1: Some("addr0002".to_string()),
+        addr2: Some("addr0003".to_string()),
+        unlock_time: None,
+        vesting: None,
+        min_recipient_amount: None,
+        deadline: None,
+        nonce: Some("1".to_string()),
+    });
+    assert_matches_schema(hook_msg.clone());
+    assert_matches_schema(Cw20HookMsg::DepositWeighted { recipients: vec![("addr0002".to_string(), 1)] });
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&hook_msg).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    assert_matches_schema(deposit_msg.clone());
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let withdraw_msg = ExecuteMsg::Withdraw { amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    assert_matches_schema(withdraw_msg.clone());
+    assert_matches_schema(ExecuteMsg::WithdrawAll {});
+
+    for msg in [
+        QueryMsg::Config {},
+        QueryMsg::TokenBalance {},
+        QueryMsg::Deposit { id: 1 },
+        QueryMsg::DepositsByRecipient { addr: "addr0002".to_string(), start_after: None, limit: Some(10) },
+    ] {
+        assert_matches_schema(msg);
+    }
+    assert_matches_schema(MigrateMsg {});
+    assert_matches_schema(SudoMsg::SetFee { fee_numerator: Some(10), fee_denominator: Some(1000), fee_bps: None });
+
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config{}).unwrap()).unwrap();
+    assert_matches_schema(config);
+    let fee_info: FeeInfoResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeInfo{}).unwrap()).unwrap();
+    assert_matches_schema(fee_info);
+    let owner: OwnerResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Owner{}).unwrap()).unwrap();
+    assert_matches_schema(owner);
+    let stats: StatsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Stats{}).unwrap()).unwrap();
+    assert_matches_schema(stats);
+    let withdrawable: WithdrawableResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap();
+    assert_matches_schema(withdrawable);
+    let preview: PreviewResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PreviewDeposit{ amount: Uint128::from(1000u128) }).unwrap()).unwrap();
+    assert_matches_schema(preview);
+    let preview: PreviewWithdrawResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PreviewWithdraw{ addr: "addr0002".to_string(), amount: Uint128::from(300u128) }).unwrap()).unwrap();
+    assert_matches_schema(preview);
+    let record: DepositRecord = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Deposit{ id: 1 }).unwrap()).unwrap();
+    assert_matches_schema(record);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    let data: WithdrawResponse = from_binary(&res.data.unwrap()).unwrap();
+    assert_matches_schema(data);
+}
+
+#[test]
+fn query_response_shapes() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Owner{}).unwrap();
+    assert_eq!(r#"{"owner":"addr0000"}"#, String::from_utf8(res.to_vec()).unwrap());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap();
+    assert_eq!(r#"{"amount":"0"}"#, String::from_utf8(res.to_vec()).unwrap());
+}
+
+#[test]
+fn execute_deposit_slippage() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 475 each at the current fee
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: Some(Uint128::from(475u128)),
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+
+    // the fee is raised before the next deposit lands
+    let update_msg = ExecuteMsg::UpdateFee{ fee_numerator: Some(100), fee_denominator: Some(1000), fee_bps: None };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), update_msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::SlippageExceeded {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_empty_address() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::EmptyAddress { field } => assert_eq!("addr2", field),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_deadline() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deadline = mock_env().block.time.plus_seconds(60);
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: Some(deadline),
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let mut env = mock_env();
+    env.block.time = deadline;
+    execute(deps.as_mut(), env.clone(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+
+    env.block.time = deadline.plus_seconds(1);
+    let res = execute(deps.as_mut(), env, mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::Expired {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_deposit_duplicate_nonce() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: Some("payout-1".to_string()),
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+
+    // the retry is not credited again
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::DuplicateNonce {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // nonces are per depositor
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: Some("payout-1".to_string()),
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+}
+
+#[test]
+fn execute_revoke() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let revoke_msg = ExecuteMsg::Revoke{ recipient: "addr0002".to_string(), amount: Uint128::from(400u128) };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), revoke_msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), revoke_msg.clone()).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "revoke"),
+            attr("recipient", "addr0002"),
+            attr("amount", "400"),
+            attr("remaining", "75"),
+            attr("recovered_to", "fee"),
+        ]
+    );
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(75u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(450u128), fee_collected);
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(550u128), total);
+
+    // only 75 are left to revoke
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), revoke_msg).unwrap_err();
+    match res {
+        ContractError::InsufficientFunds { requested, available } => {
+            assert_eq!(Uint128::from(400u128), requested);
+            assert_eq!(Uint128::from(75u128), available);
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_revoke_to_recovery_addr() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        recovery_addr: Some("addr0009".to_string()),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let revoke_msg = ExecuteMsg::Revoke{ recipient: "addr0002".to_string(), amount: Uint128::from(475u128) };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), revoke_msg).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0009".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee_collected);
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(950u128), total);
+}
+
+#[test]
+fn query_fee_info() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        fee_numerator: Some(2500),
+        fee_denominator: Some(1000000),
+        max_fee: Some(Uint128::from(1000u128)),
+        rounding: Some(RoundingMode::RoundUp),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let fee_info: FeeInfoResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeInfo{}).unwrap()).unwrap();
+    assert_eq!(
+        FeeInfoResponse {
+            fee_numerator: 2500,
+            fee_denominator: 1000000,
+            fee_bps: 25,
+            max_fee: Some(Uint128::from(1000u128)),
+            rounding: RoundingMode::RoundUp,
+        },
+        fee_info
+    );
+}
+
+#[test]
+fn query_preview_withdraw() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let preview: PreviewWithdrawResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PreviewWithdraw{ addr: "addr0002".to_string(), amount: Uint128::from(300u128) }).unwrap()).unwrap();
+    assert_eq!(
+        PreviewWithdrawResponse {
+            amount: Uint128::from(300u128),
+            remaining: Uint128::from(175u128),
+        },
+        preview
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::PreviewWithdraw{ addr: "addr0002".to_string(), amount: Uint128::from(500u128) }).unwrap_err();
+    match res {
+        StdError::GenericErr { msg, .. } => assert_eq!(msg, "Insufficient funds: requested 500, available 475"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // a frozen account cannot withdraw, so neither can its preview
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::FreezeAccount{ addr: "addr0002".to_string() }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::PreviewWithdraw{ addr: "addr0002".to_string(), amount: Uint128::from(300u128) }).unwrap_err();
+    match res {
+        StdError::GenericErr { msg, .. } => assert_eq!(msg, "Account frozen"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_withdraw_with_fee() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        withdraw_fee_bps: Some(100),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let preview: PreviewWithdrawResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PreviewWithdraw{ addr: "addr0002".to_string(), amount: Uint128::from(300u128) }).unwrap()).unwrap();
+    assert_eq!(Uint128::from(297u128), preview.amount);
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(300u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0001".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr0002".to_string(),
+                    amount: Uint128::from(297u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            1,
+        )]
+    );
+    assert!(res.attributes.contains(&attr("withdraw_fee", "3")));
+
+    // the full amount is debited
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(175u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(53u128), fee_collected);
+
+    // a failed transfer gives the fee back
+    reply(deps.as_mut(), mock_env(), Reply {
+        id: 1,
+        result: SubMsgResult::Err("transfer failed".to_string()),
+    }).unwrap();
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(475u128), withdrawable);
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee_collected);
+}
+
+#[test]
+fn query_withdrawable_batch() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let addrs = vec!["addr0002".to_string(), "addr0003".to_string(), "addr0004".to_string()];
+    let batch: Vec<(String, Uint128)> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::WithdrawableBatch{ addrs }).unwrap()).unwrap();
+    assert_eq!(
+        vec![
+            ("addr0002".to_string(), Uint128::from(475u128)),
+            ("addr0003".to_string(), Uint128::from(475u128)),
+            ("addr0004".to_string(), Uint128::zero()),
+        ],
+        batch
+    );
+
+    let addrs = (0..51).map(|i| format!("addr{:04}", i)).collect();
+    query(deps.as_ref(), mock_env(), QueryMsg::WithdrawableBatch{ addrs }).unwrap_err();
+}
+
+#[test]
+fn execute_fee_collected_missing() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // simulate a contract migrated from code without the item
+    FEE_COLLECTED.remove(deps.as_mut().storage);
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), fee_collected);
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(50u128), fee_collected);
+
+    FEE_COLLECTED.remove(deps.as_mut().storage);
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert!(res.attributes.contains(&attr("amount", "0")));
+}
+
+#[test]
+fn execute_deposit_forbid_owner_recipient() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        forbid_owner_recipient: Some(true),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0000".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::OwnerRecipient {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositMany{
+            recipients: vec!["addr0002".to_string(), "addr0000".to_string()],
+        }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap_err();
+    match res {
+        ContractError::OwnerRecipient {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn execute_reset_fee_accounting() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_instantiate_msg();
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), ExecuteMsg::ResetFeeAccounting {});
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::ResetFeeAccounting {}).unwrap();
+    assert_eq!(0, res.messages.len());
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "reset_fee_accounting"),
+            attr("previous", "50"),
+        ]
+    );
+
+    let fee_collected: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeeCollected{}).unwrap()).unwrap();
+    assert_eq!(Uint128::zero(), fee_collected);
+}
+
+#[test]
+fn execute_deposit_deflationary_token() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        deflationary_token: Some(true),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    // the token kept 2% of the transfer
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(980u128))],
+    )]);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg.clone()).unwrap();
+    assert_eq!(attr("amount", "980"), res.attributes[1]);
+    assert_eq!(attr("fee", "49"), res.attributes[2]);
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(465u128), withdrawable);
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0003".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(466u128), withdrawable);
+
+    // only what arrived on top of the owed balance is credited
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1960u128))],
+    )]);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+    assert_eq!(attr("amount", "980"), res.attributes[1]);
+
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1862u128), total);
+
+    // a withdrawal sends the full amount debited, the token's fee comes out of what the recipient receives
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(465u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap();
+    assert_eq!(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "asset0001".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0002".to_string(),
+                amount: Uint128::from(465u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }),
+        res.messages[0].msg,
+    );
+
+    let withdrawable: Uint128 = from_binary::<WithdrawableResponse>(&query(deps.as_ref(), mock_env(), QueryMsg::Withdrawable{ addr: "addr0002".to_string() }).unwrap()).unwrap().amount;
+    assert_eq!(Uint128::from(465u128), withdrawable);
+    let total: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalWithdrawable{}).unwrap()).unwrap();
+    assert_eq!(Uint128::from(1397u128), total);
+
+    // exact amounts cannot match what the token delivered
+    let batch_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::BatchDeposit{ deposits: vec![] }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), batch_msg).unwrap_err();
+    match res {
+        ContractError::DeflationaryExactAmounts {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let exact_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::DepositExact{ recipients: vec![("addr0002".to_string(), Uint128::from(950u128))] }).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), exact_msg).unwrap_err();
+    match res {
+        ContractError::DeflationaryExactAmounts {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn clamp_limit_bounds() {
+    assert_eq!(10, clamp_limit(None));
+    assert_eq!(1, clamp_limit(Some(1)));
+    assert_eq!(30, clamp_limit(Some(30)));
+    assert_eq!(30, clamp_limit(Some(31)));
+    assert_eq!(30, clamp_limit(Some(u32::MAX)));
+}
+
+#[test]
+fn execute_withdraw_check_contract_balance() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        check_contract_balance: Some(true),
+        ..default_instantiate_msg()
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        msg: to_binary(&Cw20HookMsg::Deposit(DepositMsg {
+            addr1: Some("addr0002".to_string()),
+            addr2: Some("addr0003".to_string()),
+            unlock_time: None,
+            vesting: None,
+            min_recipient_amount: None,
+            deadline: None,
+            nonce: None,
+        })).unwrap(),
+        amount: Uint128::from(1000u128),
+    });
+
+    execute(deps.as_mut(), mock_env(), mock_info("asset0001", &[]), deposit_msg).unwrap();
+
+    // the contract holds less than it owes
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(400u128))],
+    )]);
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(475u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), withdraw_msg).unwrap_err();
+    match res {
+        ContractError::InsufficientContractBalance {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let withdraw_msg = ExecuteMsg::Withdraw{ amount: Uint128::from(400u128), recipient: None, forward_contract: None, forward_msg: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0003", &[]), withdraw_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+
+    // fee withdrawals are checked as well
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(49u128))],
+    )]);
+
+    let withdraw_fee_msg = ExecuteMsg::WithdrawFee{ recipient: None, amount: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg.clone()).unwrap_err();
+    match res {
+        ContractError::InsufficientContractBalance {} => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    deps.querier.with_token_balances(&[(
+        &"asset0001".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(50u128))],
+    )]);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), withdraw_fee_msg).unwrap();
+    assert_eq!(1, res.messages.len());
+}